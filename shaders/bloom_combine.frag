@@ -0,0 +1,14 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D image;
+uniform sampler2D bloom;
+uniform float intensity;
+
+void main()
+{
+    vec3 color = texture(image, TexCoords).rgb + texture(bloom, TexCoords).rgb * intensity;
+    FragColor = vec4(color, 1.0);
+}
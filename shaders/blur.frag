@@ -0,0 +1,20 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D image;
+uniform vec2 direction; // one texel along the axis being blurred
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+// one axis of a separable 9 tap gaussian
+void main()
+{
+    vec3 result = texture(image, TexCoords).rgb * weights[0];
+    for (int i = 1; i < 5; i++) {
+        result += texture(image, TexCoords + direction * float(i)).rgb * weights[i];
+        result += texture(image, TexCoords - direction * float(i)).rgb * weights[i];
+    }
+    FragColor = vec4(result, 1.0);
+}
@@ -0,0 +1,10 @@
+// written once per frame by Camera::send_uniforms
+layout (std140) uniform Camera {
+    mat4 proj;
+    mat4 view;
+    vec4 cameraPos;
+};
+
+vec4 worldToClip(vec3 pos) {
+    return proj * view * vec4(pos, 1.0);
+}
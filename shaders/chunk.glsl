@@ -0,0 +1,8 @@
+// chunk meshes share one vertex buffer, handed out in pages of `pageVertices` that each belong to
+// one chunk (see MeshArena). gl_VertexID counts from the start of the buffer, base vertex included
+uniform samplerBuffer chunkOrigins;
+uniform int pageVertices;
+
+vec3 chunkOrigin() {
+    return texelFetch(chunkOrigins, gl_VertexID / pageVertices).xyz;
+}
@@ -0,0 +1,13 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D screenTexture;
+uniform float opacity;
+
+void main()
+{
+    vec3 col = texture(screenTexture, TexCoords * 1.0).rgb;
+    FragColor = vec4(col, opacity);
+}
@@ -0,0 +1,36 @@
+#version 330
+
+out vec4 frag_color;
+
+in vec3 outColor;
+in vec3 worldPos;
+flat in vec3 faceNormal;
+in vec2 uv;
+flat in float layer;
+
+uniform sampler2DArray blockTextures;
+
+const float ALPHA_CUTOFF = 0.1; // the see-through texels of glass and leaves
+
+#include "camera.glsl"
+#include "sky.glsl"
+#ifdef SHADOWS
+#include "shadows.glsl"
+#endif
+
+void main() {
+    vec3 normal = faceNormal;
+    float shadow = 1.0;
+#ifdef SHADOWS
+    shadow = shadowFactor(worldPos, normal);
+#endif
+    vec4 texel = texture(blockTextures, vec3(uv, layer));
+    if (texel.a < ALPHA_CUTOFF) {
+        discard;
+    }
+    vec3 color = outColor * texel.rgb * skyLight(normal, shadow);
+#ifdef FOG
+    color = applyFog(color, worldPos);
+#endif
+    frag_color = vec4(color, texel.a); // only blended in the translucent pass
+}
@@ -0,0 +1,22 @@
+#version 330
+
+out vec3 outColor;
+out vec3 worldPos;
+flat out vec3 faceNormal;
+out vec2 uv;
+flat out float layer;
+
+#include "camera.glsl"
+#include "chunk.glsl"
+#include "voxel.glsl"
+
+const float AO_STRENGTH = 0.6; // how dark a fully occluded corner gets
+
+void main() {
+    worldPos = voxelPosition() + chunkOrigin();
+    gl_Position = worldToClip(worldPos);
+    faceNormal = voxelNormal();
+    uv = voxelUv();
+    layer = voxelLayer();
+    outColor = voxelColor() * (1.0 - AO_STRENGTH * (1.0 - voxelAo())) * voxelLight();
+}
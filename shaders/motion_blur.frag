@@ -0,0 +1,15 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D image;
+uniform sampler2D previous; // this pass's output last frame
+uniform float strength;
+
+void main()
+{
+    vec3 current = texture(image, TexCoords).rgb;
+    vec3 last = texture(previous, TexCoords).rgb;
+    FragColor = vec4(mix(current, last, strength), 1.0);
+}
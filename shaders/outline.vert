@@ -0,0 +1,9 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+
+#include "camera.glsl"
+
+void main()
+{
+    gl_Position = worldToClip(aPos);
+}
@@ -0,0 +1,15 @@
+#version 330
+
+in vec2 uv;
+flat in float layer;
+
+uniform sampler2DArray blockTextures;
+
+const float ALPHA_CUTOFF = 0.1; // same as mesh.frag, so glass and leaves shadow what they draw
+
+// depth only, the see-through texels of alpha tested blocks are left out
+void main() {
+    if (texture(blockTextures, vec3(uv, layer)).a < ALPHA_CUTOFF) {
+        discard;
+    }
+}
@@ -0,0 +1,15 @@
+#version 330
+
+out vec2 uv;
+flat out float layer;
+
+#include "chunk.glsl"
+#include "voxel.glsl"
+
+uniform mat4 lightSpace;
+
+void main() {
+    gl_Position = lightSpace * vec4(voxelPosition() + chunkOrigin(), 1.0);
+    uv = voxelUv();
+    layer = voxelLayer();
+}
@@ -0,0 +1,37 @@
+// written by ShadowMaps::render, include camera.glsl first
+layout (std140) uniform Shadows {
+    mat4 lightSpace[4];
+    vec4 cascadeEnds; // view space depth where each cascade stops
+    vec4 texelSizes; // world units covered by one shadow map texel, per cascade
+};
+
+uniform sampler2DArrayShadow shadowMap;
+
+// 1 where the sun (or moon) reaches `pos`, 0 in full shadow, with 3x3 pcf in between
+float shadowFactor(vec3 pos, vec3 normal) {
+    float depth = -(view * vec4(pos, 1.0)).z;
+    if (depth > cascadeEnds[3]) {
+        return 1.0;
+    }
+    int cascade = 0;
+    while (cascade < 3 && depth > cascadeEnds[cascade]) {
+        cascade++;
+    }
+
+    // pushed out along the normal by about a texel so faces don't shadow themselves
+    vec3 offsetPos = pos + normal * texelSizes[cascade] * 1.5;
+    vec4 lightPos = lightSpace[cascade] * vec4(offsetPos, 1.0);
+    vec3 coords = lightPos.xyz / lightPos.w * 0.5 + 0.5;
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+
+    vec2 texel = 1.0 / vec2(textureSize(shadowMap, 0).xy);
+    float lit = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            lit += texture(shadowMap, vec4(coords.xy + vec2(x, y) * texel, float(cascade), coords.z));
+        }
+    }
+    return lit / 9.0;
+}
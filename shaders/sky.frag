@@ -0,0 +1,12 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec3 ViewDir;
+
+#include "camera.glsl"
+#include "sky.glsl"
+
+void main()
+{
+    FragColor = vec4(skyColor(normalize(ViewDir)), 1.0);
+}
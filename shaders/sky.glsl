@@ -0,0 +1,36 @@
+// written by Sky::upload, include camera.glsl first
+layout (std140) uniform Sky {
+    vec4 zenithColor;
+    vec4 horizonColor;
+    vec4 sunDirection; // towards the sun
+    vec4 sunColor;
+    vec4 fogRange; // x: where fog starts, y: where it's fully opaque, in blocks from the camera
+    vec4 lightDirection; // towards the sun by day, the moon by night
+    vec4 lightColor;
+    vec4 ambientColor;
+};
+
+vec3 skyGradient(vec3 dir) {
+    float height = pow(1.0 - max(dir.y, 0.0), 4.0);
+    return mix(zenithColor.rgb, horizonColor.rgb, height);
+}
+
+vec3 skyColor(vec3 dir) {
+    float sun = dot(dir, sunDirection.xyz);
+    float disc = smoothstep(0.9997, 0.9999, sun) * smoothstep(-0.05, 0.02, sunDirection.y);
+    float glow = pow(max(sun, 0.0), 256.0) * 0.5;
+    return skyGradient(dir) + sunColor.rgb * (disc * 4.0 + glow);
+}
+
+// sunlight/moonlight on a surface facing `normal` plus the ambient light,
+// `shadow` is how much of the direct light gets there
+vec3 skyLight(vec3 normal, float shadow) {
+    return ambientColor.rgb + lightColor.rgb * max(dot(normal, lightDirection.xyz), 0.0) * shadow;
+}
+
+// fades into the sky behind the fragment, so whatever is at the edge of the world blends into it
+vec3 applyFog(vec3 color, vec3 pos) {
+    vec3 toPos = pos - cameraPos.xyz;
+    float fog = smoothstep(fogRange.x, fogRange.y, length(toPos));
+    return mix(color, skyGradient(normalize(toPos)), fog);
+}
@@ -0,0 +1,14 @@
+#version 330 core
+out vec3 ViewDir;
+
+#include "camera.glsl"
+
+// fullscreen triangle on the far plane, the world space direction through each corner gets interpolated
+void main()
+{
+    vec2 pos = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    mat4 rotation = mat4(mat3(view)); // only where the camera looks, not where it is
+    vec4 dir = inverse(proj * rotation) * vec4(pos, 1.0, 1.0);
+    ViewDir = dir.xyz / dir.w;
+    gl_Position = vec4(pos, 1.0, 1.0);
+}
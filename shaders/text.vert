@@ -0,0 +1,18 @@
+#version 330 core
+layout (location = 0) in vec2 aPos;
+layout (location = 1) in vec2 aTexCoords;
+layout (location = 2) in vec4 aColor;
+
+uniform float screenWidth;
+uniform float screenHeight;
+
+out vec2 TexCoords;
+out vec4 Color;
+
+void main()
+{
+    TexCoords = aTexCoords;
+    Color = aColor;
+    vec2 ndc = vec2(aPos.x / screenWidth, 1.0 - aPos.y / screenHeight) * 2.0 - 1.0;
+    gl_Position = vec4(ndc, 0.0, 1.0);
+}
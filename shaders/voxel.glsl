@@ -0,0 +1,65 @@
+// unpacks VoxelVertex, see mesh.rs for the bit layout
+layout (location = 0) in uvec2 aVoxel;
+
+// written once at startup by block::upload_palette
+layout (std140) uniform Blocks {
+    vec4 blockColors[256];
+    uvec4 blockLayers[256]; // texture array layer per face, two faces per component, w is the tinted faces
+    vec4 biomeTints[16];
+};
+
+// same order as FACES in world.rs
+const vec3 FACE_NORMALS[6] = vec3[6](
+    vec3(-1.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0),
+    vec3(0.0, -1.0, 0.0), vec3(0.0, 1.0, 0.0),
+    vec3(0.0, 0.0, -1.0), vec3(0.0, 0.0, 1.0)
+);
+
+// inside the chunk, add chunkOrigin() for world space
+vec3 voxelPosition() {
+    float drop = float((aVoxel.x >> 24u) & 15u) / 16.0;
+    return vec3(aVoxel.x & 31u, float((aVoxel.x >> 5u) & 31u) - drop, (aVoxel.x >> 10u) & 31u);
+}
+
+uint voxelFace() {
+    return (aVoxel.x >> 15u) & 7u;
+}
+
+vec3 voxelNormal() {
+    return FACE_NORMALS[int(voxelFace())];
+}
+
+// 0 fully occluded, 1 open
+float voxelAo() {
+    return float((aVoxel.x >> 18u) & 3u) / 3.0;
+}
+
+float voxelLight() {
+    return float((aVoxel.x >> 20u) & 15u) / 15.0;
+}
+
+uint voxelBlock() {
+    return aVoxel.y & 255u;
+}
+
+uint voxelBiome() {
+    return (aVoxel.y >> 18u) & 15u;
+}
+
+// the block's color, times its biome's tint on the faces that take one
+vec3 voxelColor() {
+    vec3 color = blockColors[voxelBlock()].rgb;
+    bool tinted = ((blockLayers[voxelBlock()].w >> voxelFace()) & 1u) != 0u;
+    return tinted ? color * biomeTints[voxelBiome()].rgb : color;
+}
+
+// in blocks, repeats once per block
+vec2 voxelUv() {
+    return vec2((aVoxel.y >> 8u) & 31u, (aVoxel.y >> 13u) & 31u);
+}
+
+float voxelLayer() {
+    uint face = voxelFace();
+    uint pair = blockLayers[voxelBlock()][int(face / 2u)];
+    return float((face % 2u == 0u) ? pair & 0xffffu : pair >> 16u);
+}
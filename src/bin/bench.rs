@@ -0,0 +1,147 @@
+use std::{mem::size_of, sync::Arc, time::{Duration, Instant}};
+
+use cgmath::{vec3, Vector3};
+
+use minecraftp::{
+    biomes::BiomeGenerator,
+    camera_path::CameraPath,
+    mesh::VoxelVertex,
+    terrain::{EdgeMode, HeightmapGenerator, PerlinGenerator, TerrainGenerator},
+    world::{Chunk, Mesher, Voxel, World, CHUNK_SIZE},
+};
+
+// headless benchmark for terrain generation and meshing, never opens a window.
+// usage: cargo run --release --bin bench -- [--chunks 128] [--path camera_path.txt] [--seed 0]
+//        [--heightmap map.png | --perlin]
+// the terrain is the game's biome generator unless a heightmap or the plain perlin baseline is asked for
+
+struct Timings {
+    samples: Vec<Duration>,
+}
+
+impl Timings {
+    fn new() -> Self {
+        Self { samples: Vec::new() }
+    }
+
+    fn time<T>(&mut self, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let out = f();
+        self.samples.push(start.elapsed());
+        out
+    }
+
+    fn report(&self, name: &str) {
+        if self.samples.is_empty() {
+            println!("{name:<24} no samples");
+            return;
+        }
+
+        let total: Duration = self.samples.iter().sum();
+        let min = self.samples.iter().min().unwrap();
+        let max = self.samples.iter().max().unwrap();
+        let avg = total / self.samples.len() as u32;
+
+        println!(
+            "{name:<24} {:>6} runs  avg {:>9.3}ms  min {:>9.3}ms  max {:>9.3}ms  total {:>9.1}ms",
+            self.samples.len(),
+            avg.as_secs_f64() * 1000.0,
+            min.as_secs_f64() * 1000.0,
+            max.as_secs_f64() * 1000.0,
+            total.as_secs_f64() * 1000.0,
+        );
+    }
+}
+
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned()
+}
+
+fn megabytes(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let target_chunks: usize = arg_value(&args, "--chunks").and_then(|n| n.parse().ok()).unwrap_or(128);
+    let seed: u32 = arg_value(&args, "--seed").and_then(|n| n.parse().ok()).unwrap_or(0);
+
+    let generator: Arc<dyn TerrainGenerator> = match arg_value(&args, "--heightmap") {
+        Some(path) => match HeightmapGenerator::from_file(&path, 0.0, 64.0, EdgeMode::Tile) {
+            Ok(heightmap) => Arc::new(heightmap),
+            Err(e) => {
+                println!("could not load heightmap {path}: {e}");
+                return;
+            }
+        },
+        None if args.iter().any(|a| a == "--perlin") => Arc::new(PerlinGenerator::new(seed)),
+        None => Arc::new(BiomeGenerator::new(seed)),
+    };
+
+    // either a recorded flythrough or a straight line along +x, half a chunk per step
+    let path: Vec<Vector3<f32>> = match arg_value(&args, "--path") {
+        Some(file) => match CameraPath::load(&file) {
+            Ok(path) => path.keyframes.iter().map(|k| k.pos).collect(),
+            Err(e) => {
+                println!("could not load camera path {file}: {e}");
+                return;
+            }
+        },
+        None => (0..100_000).map(|i| vec3(i as f32 * CHUNK_SIZE as f32 * 0.5, 0.0, 0.0)).collect(),
+    };
+
+    println!("generating {target_chunks} chunks of {CHUNK_SIZE}^3 voxels along a {} point path", path.len());
+
+    let mut world = World::headless(generator.clone());
+    let mut update_timings = Timings::new();
+    for pos in &path {
+        if world.chunks_generated >= target_chunks {
+            break;
+        }
+        world.camera_pos = *pos;
+        let start = Instant::now();
+        world.update().await;
+        update_timings.samples.push(start.elapsed());
+    }
+
+    // generation on its own, without the loading/unloading bookkeeping of World::update
+    let positions: Vec<Vector3<i32>> = world.chunks.keys().copied().collect();
+    let mut generate_timings = Timings::new();
+    for pos in &positions {
+        let p = vec3(pos.x as f32, pos.y as f32, pos.z as f32);
+        generate_timings.time(|| Chunk::with_generator(p, generator.as_ref()));
+    }
+
+    println!();
+    update_timings.report("World::update");
+    generate_timings.report("Chunk::with_generator");
+
+    let voxel_bytes = world.chunks.len() * CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE * size_of::<Voxel>();
+
+    let mut mesh_reports = Vec::new();
+    for mesher in Mesher::ALL {
+        let mut timings = Timings::new();
+        let (mut vertices, mut indices) = (0, 0);
+
+        for chunk in world.chunks.values() {
+            let mesh = timings.time(|| chunk.gen_mesh_data(mesher, &world.chunks));
+            vertices += mesh.vertex_count();
+            indices += mesh.index_count();
+        }
+
+        timings.report(&format!("mesh {}", mesher.name()));
+        mesh_reports.push((mesher, vertices, indices));
+    }
+
+    println!();
+    println!("{} chunks loaded, {} generated in total", world.chunks.len(), world.chunks_generated);
+    println!("voxel data {:>10.2}MB", megabytes(voxel_bytes));
+    for (mesher, vertices, indices) in mesh_reports {
+        let bytes = vertices * size_of::<VoxelVertex>() + indices * size_of::<u32>();
+        println!(
+            "mesh {:<12} {:>10} triangles  {:>10} vertices  {:>8.2}MB",
+            mesher.name(), indices / 3, vertices, megabytes(bytes),
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use minecraftp::glsl::{load_include, preprocess, Defines};
+
+// prints a shader the way the driver will see it, after includes and defines.
+// usage: cargo run --bin glsl -- shaders/mesh.vert [-D FOG] [-D FOG_DENSITY=0.02]
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut path = None;
+    let mut defines = Defines::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "-D" {
+            let Some(define) = iter.next() else {
+                println!("-D needs a NAME or NAME=VALUE");
+                return;
+            };
+            let (name, value) = define.split_once('=').unwrap_or((define, ""));
+            defines.insert(name.to_string(), value.to_string());
+        } else {
+            path = Some(arg);
+        }
+    }
+
+    let Some(path) = path else {
+        println!("usage: glsl <file> [-D NAME[=VALUE]]...");
+        return;
+    };
+
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            println!("could not read {path}: {e}");
+            return;
+        },
+    };
+
+    match preprocess(&source, &defines, &load_include) {
+        Ok(expanded) => print!("{}", expanded.code),
+        Err(e) => println!("{path}: {e}"),
+    }
+}
@@ -0,0 +1,236 @@
+use cgmath::Vector3;
+use noise::{core::perlin::perlin_2d, permutationtable::PermutationTable, Vector2 as NVec2};
+
+use crate::{block, terrain::{voxel_index, TerrainGenerator}, util::smoothstep, world::{Voxel, CHUNK_SIZE}};
+
+pub const MAX_BIOMES: usize = 16; // 4 bits in `VoxelVertex`, std140 `Blocks`
+
+pub const PLAINS: u8 = 0;
+pub const FOREST: u8 = 1;
+pub const DESERT: u8 = 2;
+pub const SNOWY: u8 = 3;
+pub const BEACH: u8 = 4;
+pub const OCEAN: u8 = 5;
+pub const MOUNTAINS: u8 = 6;
+pub const PEAKS: u8 = 7;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Biome {
+    pub name: &'static str,
+    pub surface: u8, // the top block of a column
+    pub subsurface: u8, // the `depth` blocks under it, stone below that
+    pub depth: u32,
+    pub tint: [f32; 3], // multiplied with grass, leaves and water, see `block::Tint`
+}
+
+// indexed by id. plains comes first and doesn't tint, generators without biomes get it everywhere
+pub const BIOMES: [Biome; 8] = [
+    Biome { name: "plains", surface: block::GROUND, subsurface: block::DIRT, depth: 3, tint: [1.0, 1.0, 1.0] },
+    Biome { name: "forest", surface: block::GROUND, subsurface: block::DIRT, depth: 4, tint: [0.7, 0.9, 0.6] },
+    Biome { name: "desert", surface: block::SAND, subsurface: block::SAND, depth: 5, tint: [1.1, 1.0, 0.6] },
+    Biome { name: "snowy", surface: block::SNOW, subsurface: block::DIRT, depth: 3, tint: [0.8, 0.9, 1.0] },
+    Biome { name: "beach", surface: block::SAND, subsurface: block::SAND, depth: 3, tint: [1.0, 1.0, 0.9] },
+    Biome { name: "ocean", surface: block::GRAVEL, subsurface: block::SAND, depth: 2, tint: [0.6, 0.8, 1.0] },
+    Biome { name: "mountains", surface: block::STONE, subsurface: block::STONE, depth: 0, tint: [0.8, 0.9, 0.8] },
+    Biome { name: "peaks", surface: block::SNOW, subsurface: block::STONE, depth: 1, tint: [0.8, 0.9, 1.0] },
+];
+
+pub const SEA_LEVEL: f32 = 0.0;
+const MOUNTAIN_LINE: f32 = SEA_LEVEL + 40.0;
+const SNOW_LINE: f32 = SEA_LEVEL + 60.0;
+
+// continentalness to height above sea level, linear between the points. low is open ocean,
+// high is far inland
+const CONTINENT_SPLINE: [(f32, f32); 6] = [(-1.0, -30.0), (-0.3, -12.0), (-0.1, -1.0), (0.05, 3.0), (0.3, 12.0), (1.0, 30.0)];
+const HILL_HEIGHT: f32 = 80.0; // where erosion is lowest
+const LAPSE_RATE: f32 = 1.0 / 150.0; // how much colder it gets per block above the sea
+
+// what the generator decides for one column before filling it
+#[derive(Clone, Copy, Debug)]
+pub struct Column {
+    pub height: f32,
+    pub biome: u8,
+}
+
+// terrain from layers of 2d noise. continentalness sets the base height (oceans, coasts, inland),
+// erosion how hilly it is on top of that, and temperature and humidity, along with the height,
+// pick the biome whose blocks cover the surface. the same seed always gives the same world
+pub struct BiomeGenerator {
+    pub seed: u32,
+    continentalness: PermutationTable,
+    erosion: PermutationTable,
+    hills: PermutationTable,
+    temperature: PermutationTable,
+    humidity: PermutationTable,
+}
+
+impl BiomeGenerator {
+    pub fn new(seed: u32) -> Self {
+        let layer = |i: u32| PermutationTable::new(seed.wrapping_mul(5).wrapping_add(i));
+
+        Self {
+            seed,
+            continentalness: layer(0),
+            erosion: layer(1),
+            hills: layer(2),
+            temperature: layer(3),
+            humidity: layer(4),
+        }
+    }
+
+    pub fn column(&self, x: f32, z: f32) -> Column {
+        let continentalness = fbm(&self.continentalness, x, z, 600.0, 4);
+        // fbm rarely gets near its ends, so the range that matters is stretched to 0..1
+        let ruggedness = 1.0 - smoothstep(-0.35, 0.25, fbm(&self.erosion, x, z, 300.0, 3));
+        let inland = smoothstep(-0.1, 0.2, continentalness);
+        let hills = fbm(&self.hills, x, z, 80.0, 4) * 0.5 + 0.5;
+
+        let height = SEA_LEVEL
+            + spline(&CONTINENT_SPLINE, continentalness)
+            + hills * ruggedness.powi(2) * HILL_HEIGHT * inland;
+
+        let temperature = fbm(&self.temperature, x, z, 500.0, 2) - (height - SEA_LEVEL).max(0.0) * LAPSE_RATE;
+        let humidity = fbm(&self.humidity, x, z, 400.0, 2);
+
+        let biome = if height < SEA_LEVEL - 2.0 {
+            OCEAN
+        } else if height > SNOW_LINE {
+            PEAKS
+        } else if height > MOUNTAIN_LINE {
+            MOUNTAINS
+        } else if temperature < -0.35 {
+            SNOWY
+        } else if height < SEA_LEVEL + 2.0 {
+            BEACH
+        } else if temperature > 0.3 && humidity < 0.0 {
+            DESERT
+        } else if humidity > 0.2 {
+            FOREST
+        } else {
+            PLAINS
+        };
+
+        Column { height, biome }
+    }
+
+    // the y of the top block at a column, for putting things on the ground
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        self.column(x, z).height.floor()
+    }
+}
+
+impl TerrainGenerator for BiomeGenerator {
+    fn generate(&self, chunk_pos: Vector3<f32>, voxels: &mut [Voxel], biomes: &mut [u8]) {
+        let origin = chunk_pos * CHUNK_SIZE as f32;
+
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let column = self.column(origin.x + x as f32, origin.z + z as f32);
+                biomes[x * CHUNK_SIZE + z] = column.biome;
+                let biome = &BIOMES[column.biome as usize];
+                let top = column.height.floor() as i32;
+
+                for y in 0..CHUNK_SIZE {
+                    let world_y = origin.y as i32 + y as i32;
+                    let depth = top - world_y;
+                    let id = if depth < 0 {
+                        if (world_y as f32) < SEA_LEVEL { block::WATER } else { continue }
+                    } else if depth == 0 {
+                        biome.surface
+                    } else if depth <= biome.depth as i32 {
+                        biome.subsurface
+                    } else {
+                        block::STONE
+                    };
+                    voxels[voxel_index(x, y, z)] = Voxel::new(id);
+                }
+            }
+        }
+    }
+}
+
+// a few octaves of perlin noise, each twice as fine and half as strong. roughly -1..1.
+// every octave is shifted off the lattice, perlin noise is 0 on it and the world origin
+// would look the same for every seed
+fn fbm(hasher: &PermutationTable, x: f32, z: f32, scale: f32, octaves: u32) -> f32 {
+    let (mut sum, mut amplitude, mut frequency, mut total) = (0.0, 1.0, 1.0 / scale as f64, 0.0);
+    for octave in 0..octaves {
+        let shift = 0.37 + octave as f64 * 0.21;
+        sum += perlin_2d(NVec2::new(x as f64 * frequency + shift, z as f64 * frequency + shift), hasher) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    (sum / total) as f32
+}
+
+fn spline(points: &[(f32, f32)], t: f32) -> f32 {
+    let t = t.clamp(points[0].0, points[points.len() - 1].0);
+    for pair in points.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if t <= x1 {
+            return y0 + (y1 - y0) * (t - x0) / (x1 - x0);
+        }
+    }
+
+    points[points.len() - 1].1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a row of chunks far enough apart to cross several biomes, around sea level
+    fn generate(seed: u32) -> (Vec<Voxel>, Vec<u8>) {
+        let generator = BiomeGenerator::new(seed);
+        let (mut all_voxels, mut all_biomes) = (Vec::new(), Vec::new());
+        for i in 0..8 {
+            for y in [-1.0, 0.0] {
+                let mut voxels = vec![Voxel::air(); CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE];
+                let mut biomes = vec![PLAINS; CHUNK_SIZE * CHUNK_SIZE];
+                generator.generate(Vector3::new(i as f32 * 20.0, y, i as f32 * -13.0), &mut voxels, &mut biomes);
+                all_voxels.extend(voxels);
+                all_biomes.extend(biomes);
+            }
+        }
+
+        (all_voxels, all_biomes)
+    }
+
+    #[test]
+    fn same_seed_same_world() {
+        let (voxels, biomes) = generate(42);
+        assert_eq!((voxels.clone(), biomes.clone()), generate(42));
+
+        // the biomes written are the ones the columns pick
+        let generator = BiomeGenerator::new(42);
+        let origin = Vector3::new(20.0, 0.0, -13.0) * CHUNK_SIZE as f32;
+        let column = CHUNK_SIZE * CHUNK_SIZE * 2; // the columns of i = 1, after both of i = 0
+        assert_eq!(biomes[column + 5 * CHUNK_SIZE + 7], generator.column(origin.x + 5.0, origin.z + 7.0).biome);
+        assert!(biomes.iter().any(|biome| *biome != biomes[0]), "only {}", BIOMES[biomes[0] as usize].name);
+    }
+
+    #[test]
+    fn different_seed_different_world() {
+        let (voxels, biomes) = generate(1);
+        let (other_voxels, other_biomes) = generate(2);
+        assert_ne!(voxels, other_voxels);
+        assert_ne!(biomes, other_biomes);
+    }
+
+    #[test]
+    fn spline_clamps_and_interpolates() {
+        let points = [(-1.0, 10.0), (0.0, 20.0), (1.0, 0.0)];
+        assert_eq!(spline(&points, -5.0), 10.0);
+        assert_eq!(spline(&points, -1.0), 10.0);
+        assert_eq!(spline(&points, -0.5), 15.0);
+        assert_eq!(spline(&points, 0.0), 20.0);
+        assert_eq!(spline(&points, 0.25), 15.0);
+        assert_eq!(spline(&points, 1.0), 0.0);
+        assert_eq!(spline(&points, 7.0), 0.0);
+
+        assert_eq!(spline(&CONTINENT_SPLINE, f32::MIN), CONTINENT_SPLINE[0].1);
+        assert_eq!(spline(&CONTINENT_SPLINE, f32::MAX), CONTINENT_SPLINE[CONTINENT_SPLINE.len() - 1].1);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeSet;
+
+use crate::{biomes::{BIOMES, MAX_BIOMES}, block_textures::BlockTextures, shader::{UniformBuffer, BLOCKS_BLOCK_BINDING}};
+
+pub const AIR: u8 = 0;
+pub const GROUND: u8 = 1;
+pub const GLASS: u8 = 2;
+pub const LEAVES: u8 = 3;
+pub const WATER: u8 = 4;
+pub const LAVA: u8 = 5;
+pub const SAND: u8 = 6;
+pub const GRAVEL: u8 = 7;
+pub const STONE: u8 = 8;
+pub const DIRT: u8 = 9;
+pub const SNOW: u8 = 10;
+
+const PALETTE_SIZE: usize = 256; // one entry per possible id, std140 `Blocks`
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opacity {
+    Opaque,
+    Transparent, // texels are either fully see-through or not, like glass and leaves
+    Translucent, // blended over what's behind, like water
+}
+
+// what the renderer and physics need to know about a block id
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Block {
+    pub name: &'static str,
+    pub color: [f32; 3], // multiplied with the face textures
+    pub textures: [&'static str; 6], // png names in textures/blocks, in `FACES` order (-x, +x, -y, +y, -z, +z)
+    pub opacity: Opacity,
+    pub solid: bool, // collides and can be targeted
+    pub behaviour: Behaviour,
+    pub tint: Tint,
+}
+
+// which faces get multiplied by the tint of the biome the block is in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tint {
+    None,
+    Top, // grass, its sides and the dirt below stay as they are
+    All,
+}
+
+impl Tint {
+    // one bit per face in `FACES` order
+    fn face_mask(&self) -> u32 {
+        match self {
+            Tint::None => 0,
+            Tint::Top => 1 << 3,
+            Tint::All => 0b111111,
+        }
+    }
+}
+
+// what a block does when it or a neighbour changes, see block_updates.rs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Behaviour {
+    Static,
+    Fluid(Flow),
+    Falls, // drops while there's air or fluid below it, like sand
+}
+
+// how a fluid spreads, see fluid.rs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Flow {
+    pub reach: u8, // how many blocks it runs sideways from a source, at most 7
+    pub delay: u64, // ticks between steps
+}
+
+const FALL_DELAY: u64 = 2; // ticks per block fallen
+
+impl Block {
+    pub fn is_opaque(&self) -> bool {
+        self.opacity == Opacity::Opaque
+    }
+
+    pub fn flow(&self) -> Option<Flow> {
+        match self.behaviour {
+            Behaviour::Fluid(flow) => Some(flow),
+            _ => None,
+        }
+    }
+
+    // ticks from a change next to it to its update, `None` if it never updates
+    pub fn update_delay(&self) -> Option<u64> {
+        match self.behaviour {
+            Behaviour::Static => None,
+            Behaviour::Fluid(flow) => Some(flow.delay),
+            Behaviour::Falls => Some(FALL_DELAY),
+        }
+    }
+}
+
+// the same texture on every face
+const fn all(texture: &'static str) -> [&'static str; 6] {
+    [texture; 6]
+}
+
+// grass, logs and the like
+const fn column(top: &'static str, side: &'static str, bottom: &'static str) -> [&'static str; 6] {
+    [side, side, bottom, top, side, side]
+}
+
+// indexed by id
+pub const BLOCKS: [Block; 11] = [
+    Block { name: "air", color: [0.0, 0.0, 0.0], textures: all(""), opacity: Opacity::Transparent, solid: false, behaviour: Behaviour::Static, tint: Tint::None },
+    Block { name: "ground", color: [1.0, 1.0, 1.0], textures: column("grass_top", "grass_side", "dirt"), opacity: Opacity::Opaque, solid: true, behaviour: Behaviour::Static, tint: Tint::Top },
+    Block { name: "glass", color: [1.0, 1.0, 1.0], textures: all("glass"), opacity: Opacity::Transparent, solid: true, behaviour: Behaviour::Static, tint: Tint::None },
+    Block { name: "leaves", color: [1.0, 1.0, 1.0], textures: all("leaves"), opacity: Opacity::Transparent, solid: true, behaviour: Behaviour::Static, tint: Tint::All },
+    Block { name: "water", color: [1.0, 1.0, 1.0], textures: all("water"), opacity: Opacity::Translucent, solid: false, behaviour: Behaviour::Fluid(Flow { reach: 7, delay: 5 }), tint: Tint::All },
+    // not opaque so the blocks around it keep their faces where its surface dips below them
+    Block { name: "lava", color: [1.0, 1.0, 1.0], textures: all("lava"), opacity: Opacity::Transparent, solid: false, behaviour: Behaviour::Fluid(Flow { reach: 3, delay: 30 }), tint: Tint::None },
+    Block { name: "sand", color: [1.0, 1.0, 1.0], textures: all("sand"), opacity: Opacity::Opaque, solid: true, behaviour: Behaviour::Falls, tint: Tint::None },
+    Block { name: "gravel", color: [1.0, 1.0, 1.0], textures: all("gravel"), opacity: Opacity::Opaque, solid: true, behaviour: Behaviour::Falls, tint: Tint::None },
+    Block { name: "stone", color: [1.0, 1.0, 1.0], textures: all("stone"), opacity: Opacity::Opaque, solid: true, behaviour: Behaviour::Static, tint: Tint::None },
+    Block { name: "dirt", color: [1.0, 1.0, 1.0], textures: all("dirt"), opacity: Opacity::Opaque, solid: true, behaviour: Behaviour::Static, tint: Tint::None },
+    Block { name: "snow", color: [1.0, 1.0, 1.0], textures: all("snow"), opacity: Opacity::Opaque, solid: true, behaviour: Behaviour::Static, tint: Tint::None },
+];
+
+const MISSING: Block = Block { name: "missing", color: [1.0, 0.0, 1.0], textures: all(""), opacity: Opacity::Opaque, solid: true, behaviour: Behaviour::Static, tint: Tint::None };
+
+// for the console, by `Block::name`
+pub fn by_name(name: &str) -> Option<u8> {
+    BLOCKS.iter().position(|block| block.name == name).map(|id| id as u8)
+}
+
+pub fn block(id: u8) -> &'static Block {
+    BLOCKS.get(id as usize).unwrap_or(&MISSING)
+}
+
+// whether the face of `id` towards `neighbour` can be seen. nothing shows through opaque blocks,
+// and the faces between two of the same see-through block are left out so water or glass
+// reads as one volume instead of a stack of cubes
+pub fn face_visible(id: u8, neighbour: u8) -> bool {
+    !block(neighbour).is_opaque() && neighbour != id
+}
+
+// every block's color, face texture layers and tinted faces, and every biome's tint, in the `Blocks` uniform block, where the mesh shader
+// looks them up by id. the buffer has to be kept around for as long as anything draws chunks
+pub unsafe fn upload_palette(textures: &BlockTextures) -> UniformBuffer {
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct Palette {
+        colors: [[f32; 4]; PALETTE_SIZE],
+        layers: [[u32; 4]; PALETTE_SIZE], // two faces per component, 16 bits each, then the tint mask
+        tints: [[f32; 4]; MAX_BIOMES],
+    }
+
+    let mut palette = Palette {
+        colors: [[0.0; 4]; PALETTE_SIZE],
+        layers: [[0; 4]; PALETTE_SIZE],
+        tints: [[1.0; 4]; MAX_BIOMES],
+    };
+    let mut missing = BTreeSet::new();
+    for id in 0..PALETTE_SIZE {
+        let block = block(id as u8);
+        let [r, g, b] = block.color;
+        palette.colors[id] = [r, g, b, 1.0];
+
+        let layers = block.textures.map(|name| textures.layer(name).unwrap_or_else(|| {
+            missing.insert(name);
+            0
+        }));
+        palette.layers[id] = [
+            layers[0] | layers[1] << 16,
+            layers[2] | layers[3] << 16,
+            layers[4] | layers[5] << 16,
+            block.tint.face_mask(),
+        ];
+    }
+    for (tint, biome) in palette.tints.iter_mut().zip(&BIOMES) {
+        let [r, g, b] = biome.tint;
+        *tint = [r, g, b, 1.0];
+    }
+    for name in missing {
+        println!("WARNING::TEXTURES:: no {name}.png, faces using it only get their block color");
+    }
+
+    let buffer = UniformBuffer::new(BLOCKS_BLOCK_BINDING, size_of::<Palette>());
+    buffer.write(0, &palette);
+    buffer
+}
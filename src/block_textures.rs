@@ -0,0 +1,109 @@
+use std::{collections::HashMap, fs, path::Path, ptr};
+
+use gl::*;
+use gl::types::*;
+use image::{imageops::{self, FilterType}, RgbaImage};
+
+use crate::shader::BLOCK_TEXTURES_UNIT;
+
+pub const BLOCK_TEXTURE_DIR: &str = "textures/blocks";
+
+// every png in a folder as one layer of a mipmapped `TEXTURE_2D_ARRAY`, named by file stem.
+// layer 0 is plain white, for faces without a texture or whose png is missing, so those
+// just show the block color
+pub struct BlockTextures {
+    pub texture: u32,
+    pub size: u32, // every layer is resized to the first png's size
+    layers: HashMap<String, u32>,
+}
+
+impl BlockTextures {
+    // problems are reported and the textures involved left out, it never fails.
+    // leaves the array bound to `BLOCK_TEXTURES_UNIT`
+    pub unsafe fn load(dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref();
+        let mut paths: Vec<_> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+                .collect(),
+            Err(e) => {
+                println!("ERROR::TEXTURES:: could not read {}: {e}", dir.display());
+                Vec::new()
+            },
+        };
+        paths.sort();
+
+        let mut size = None;
+        let mut images = Vec::new();
+        let mut layers = HashMap::new();
+        for path in paths {
+            let image = match image::open(&path) {
+                Ok(image) => image.into_rgba8(),
+                Err(e) => {
+                    println!("ERROR::TEXTURES:: could not load {}: {e}", path.display());
+                    continue;
+                },
+            };
+
+            let size = *size.get_or_insert(image.width());
+            let image = if image.dimensions() != (size, size) {
+                println!("WARNING::TEXTURES:: {} is {}x{}, resizing to {size}x{size}", path.display(), image.width(), image.height());
+                imageops::resize(&image, size, size, FilterType::Nearest)
+            } else {
+                image
+            };
+
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            layers.insert(name, images.len() as u32 + 1);
+            images.push(image);
+        }
+
+        let size = size.unwrap_or(1);
+        images.insert(0, RgbaImage::from_pixel(size, size, image::Rgba([255; 4])));
+
+        let mut texture = 0;
+        GenTextures(1, &mut texture);
+        ActiveTexture(TEXTURE0 + BLOCK_TEXTURES_UNIT);
+        BindTexture(TEXTURE_2D_ARRAY, texture);
+        TexImage3D(
+            TEXTURE_2D_ARRAY, 0, RGBA8 as GLint,
+            size as GLsizei, size as GLsizei, images.len() as GLsizei,
+            0, RGBA, UNSIGNED_BYTE, ptr::null(),
+        );
+        for (layer, image) in images.iter().enumerate() {
+            TexSubImage3D(
+                TEXTURE_2D_ARRAY, 0, 0, 0, layer as GLint,
+                size as GLsizei, size as GLsizei, 1,
+                RGBA, UNSIGNED_BYTE, image.as_ptr() as *const _,
+            );
+        }
+        GenerateMipmap(TEXTURE_2D_ARRAY);
+
+        // crisp pixels up close, mipmaps so they don't shimmer far away. repeat so one quad
+        // can tile the texture over every block it covers
+        TexParameteri(TEXTURE_2D_ARRAY, TEXTURE_MIN_FILTER, NEAREST_MIPMAP_LINEAR as GLint);
+        TexParameteri(TEXTURE_2D_ARRAY, TEXTURE_MAG_FILTER, NEAREST as GLint);
+        TexParameteri(TEXTURE_2D_ARRAY, TEXTURE_WRAP_S, REPEAT as GLint);
+        TexParameteri(TEXTURE_2D_ARRAY, TEXTURE_WRAP_T, REPEAT as GLint);
+        ActiveTexture(TEXTURE0);
+
+        Self { texture, size, layers }
+    }
+
+    // `None` for a name there's no png for, "" is the white layer
+    pub fn layer(&self, name: &str) -> Option<u32> {
+        if name.is_empty() {
+            return Some(0);
+        }
+        self.layers.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+}
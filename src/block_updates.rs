@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, HashSet};
+
+use cgmath::Vector3;
+
+use crate::{block::{self, Behaviour}, fluid, integrity::{Detached, Integrity}, world::{Voxel, World}};
+
+pub const TICKS_PER_SECOND: f32 = 20.0;
+const MAX_TICKS_PER_FRAME: u32 = 10; // after a long frame the world falls behind instead of stalling the game
+
+// the voxel itself and its six neighbours, everything a change can affect
+const NOTIFIED: [Vector3<i32>; 7] = [
+    Vector3::new(0, 0, 0),
+    Vector3::new(-1, 0, 0),
+    Vector3::new(1, 0, 0),
+    Vector3::new(0, -1, 0),
+    Vector3::new(0, 1, 0),
+    Vector3::new(0, 0, -1),
+    Vector3::new(0, 0, 1),
+];
+
+// blocks reacting to changes around them, on a fixed tick. whenever a voxel is set, the blocks in and
+// next to it that have a `Behaviour` are scheduled `Block::update_delay` ticks later. what they do
+// then sets more voxels, which schedules their neighbours in turn, so a change ripples outwards
+// until things settle. removed blocks also get `Integrity` to look for what came loose
+pub struct BlockUpdates {
+    pub tick: u64,
+    pub updated: usize, // blocks updated in the last tick, for the metrics
+    pub integrity: Integrity,
+    accumulator: f32,
+    scheduled: BTreeMap<u64, HashSet<Vector3<i32>>>,
+}
+
+impl BlockUpdates {
+    pub fn new(detached: Detached) -> Self {
+        Self {
+            tick: 0,
+            updated: 0,
+            integrity: Integrity::new(detached),
+            accumulator: 0.0,
+            scheduled: BTreeMap::new(),
+        }
+    }
+
+    // blocks waiting for an update
+    pub fn pending(&self) -> usize {
+        self.scheduled.values().map(HashSet::len).sum()
+    }
+
+    pub fn schedule(&mut self, pos: Vector3<i32>, delay: u64) {
+        self.scheduled.entry(self.tick + delay).or_default().insert(pos);
+    }
+
+    // runs however many ticks `dt` adds up to
+    pub fn update(&mut self, world: &mut World, dt: f32) {
+        let tick_length = 1.0 / TICKS_PER_SECOND;
+        self.notify(world);
+
+        self.accumulator = (self.accumulator + dt).min(tick_length * MAX_TICKS_PER_FRAME as f32);
+        while self.accumulator >= tick_length {
+            self.accumulator -= tick_length;
+            self.step(world, tick_length);
+        }
+    }
+
+    fn step(&mut self, world: &mut World, tick_length: f32) {
+        self.tick += 1;
+        self.updated = 0;
+
+        while let Some(entry) = self.scheduled.first_entry() {
+            if *entry.key() > self.tick {
+                break;
+            }
+            for pos in entry.remove() {
+                if !self.integrity.is_falling(pos) {
+                    update_block(world, pos);
+                    self.updated += 1;
+                }
+            }
+        }
+        self.integrity.step(world, tick_length);
+
+        self.notify(world);
+    }
+
+    // schedules the blocks in and next to every voxel that changed since last time
+    fn notify(&mut self, world: &mut World) {
+        for (changed, previous) in world.take_changes() {
+            let removed = block::block(previous.id()).solid
+                && world.voxel_at(changed).is_some_and(|voxel| !block::block(voxel.id()).solid);
+            if removed {
+                self.integrity.check(world, changed);
+            }
+            for offset in NOTIFIED {
+                let pos = changed + offset;
+                let Some(delay) = world.voxel_at(pos).and_then(|voxel| block::block(voxel.id()).update_delay()) else {
+                    continue;
+                };
+                self.schedule(pos, delay);
+            }
+        }
+    }
+}
+
+fn update_block(world: &mut World, pos: Vector3<i32>) {
+    let Some(&voxel) = world.voxel_at(pos) else {
+        return;
+    };
+
+    match block::block(voxel.id()).behaviour {
+        Behaviour::Static => {},
+        Behaviour::Fluid(flow) => fluid::update(world, pos, voxel, flow),
+        Behaviour::Falls => fall(world, pos, voxel),
+    }
+}
+
+// one block down if there's nothing holding it up. fluid in the way is pushed out, a block landing
+// in water leaves air behind, and the fluid around fills it back in on its next update
+fn fall(world: &mut World, pos: Vector3<i32>, voxel: Voxel) {
+    let below = pos - Vector3::unit_y();
+    let supported = world
+        .voxel_at(below)
+        .is_none_or(|below| below.id() != block::AIR && block::block(below.id()).flow().is_none());
+    if supported {
+        return;
+    }
+
+    world.set_voxel(pos, Voxel::air());
+    world.set_voxel(below, voxel);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{block::{GRAVEL, GROUND, SAND, WATER}, terrain::{voxel_index, TerrainGenerator}, world::CHUNK_SIZE};
+
+    // ground at y = 0 in the chunk at the origin, air everywhere else
+    struct Floor;
+
+    impl TerrainGenerator for Floor {
+        fn generate(&self, chunk_pos: Vector3<f32>, voxels: &mut [Voxel], _biomes: &mut [u8]) {
+            if chunk_pos.y != 0.0 {
+                return;
+            }
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    voxels[voxel_index(x, 0, z)] = Voxel::ground();
+                }
+            }
+        }
+    }
+
+    fn settle(world: &mut World, updates: &mut BlockUpdates) {
+        for _ in 0..400 {
+            updates.update(world, 0.05);
+        }
+        assert_eq!(updates.pending(), 0, "still falling");
+    }
+
+    fn id_at(world: &World, x: i32, y: i32, z: i32) -> Option<u8> {
+        world.voxel_at(Vector3::new(x, y, z)).map(|voxel| voxel.id())
+    }
+
+    #[test]
+    fn mining_drops_the_whole_column() {
+        let (mut world, mut updates) = (World::headless(Arc::new(Floor)), BlockUpdates::new(Detached::Off));
+        for y in 1..=2 {
+            world.set_voxel(Vector3::new(5, y, 5), Voxel::ground());
+        }
+        for y in 3..=6 {
+            world.set_voxel(Vector3::new(5, y, 5), Voxel::new(SAND));
+        }
+        settle(&mut world, &mut updates);
+        assert_eq!(id_at(&world, 5, 6, 5), Some(SAND), "held up by the ground");
+
+        assert!(world.destroy_voxel(Vector3::new(5, 2, 5)));
+        settle(&mut world, &mut updates);
+
+        assert_eq!(id_at(&world, 5, 1, 5), Some(GROUND));
+        for y in 2..=5 {
+            assert_eq!(id_at(&world, 5, y, 5), Some(SAND), "gap at {y}");
+        }
+        assert_eq!(id_at(&world, 5, 6, 5), Some(block::AIR));
+    }
+
+    #[test]
+    fn gravel_lands_on_the_floor() {
+        let (mut world, mut updates) = (World::headless(Arc::new(Floor)), BlockUpdates::new(Detached::Off));
+        world.set_voxel(Vector3::new(10, 15, 10), Voxel::new(GRAVEL));
+        settle(&mut world, &mut updates);
+
+        assert_eq!(id_at(&world, 10, 1, 10), Some(GRAVEL));
+        for y in 2..=15 {
+            assert_eq!(id_at(&world, 10, y, 10), Some(block::AIR));
+        }
+    }
+
+    #[test]
+    fn sand_replaces_the_water_it_falls_into() {
+        let (mut world, mut updates) = (World::headless(Arc::new(Floor)), BlockUpdates::new(Detached::Off));
+        world.set_voxel(Vector3::new(12, 1, 12), Voxel::fluid(WATER, 0, false));
+        settle(&mut world, &mut updates);
+        assert_eq!(id_at(&world, 11, 1, 12), Some(WATER));
+
+        world.set_voxel(Vector3::new(12, 6, 12), Voxel::new(SAND));
+        settle(&mut world, &mut updates);
+
+        assert_eq!(id_at(&world, 12, 1, 12), Some(SAND));
+        // the source is gone, so what flowed from it drains
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                assert_ne!(id_at(&world, x, 1, z), Some(WATER), "water left at {x} {z}");
+            }
+        }
+    }
+}
@@ -1,12 +1,9 @@
 use cgmath::*;
 
-use glfw::{self, Action, Key};
-
-use crate::{cstr, shader::Shader};
+use crate::{input::{InputAction, InputState}, settings::Settings, shader::UniformBuffer};
 
 const UP: Vector3<f32> = Vector3 {x: 0.0, y: 1.0, z: 0.0};
-const SPEED: f32 = 5.0;
-const SENSITIVITY: f32 = 0.001;
+pub const CAMERA_BLOCK_SIZE: usize = 2 * 64 + 16; // std140 `Camera { mat4 proj; mat4 view; vec4 cameraPos; }`
 
 pub enum ProjectionType {
     Perspective,
@@ -32,11 +29,26 @@ pub struct Camera {
     pub dt: f32,
     last_frame: f32,
 
+    pub fly: bool, // noclip movement, otherwise the player controller moves the camera
+
+    pub fov: f32,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+    pub speed: f32,
+    pub sensitivity: f32,
+
     first_mouse: bool,
     last_x: f32,
     last_y: f32,
 }
 
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Camera {
     pub fn new() -> Self {
         let (pitch, yaw): (f32, f32) = (0.0, 0.0);
@@ -75,12 +87,27 @@ impl Camera {
             dt: 0.0,
             last_frame: 0.0,
 
+            fly: true,
+
+            fov: 70.0,
+            aspect: 1.0,
+            near: 0.1,
+            far: 1000.0,
+            speed: 5.0,
+            sensitivity: 0.001,
+
             first_mouse: true,
             last_x: 400.0,
             last_y: 400.0,
         }
     }
 
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.fov = settings.fov;
+        self.speed = settings.speed;
+        self.sensitivity = settings.sensitivity;
+    }
+
     pub fn update(&mut self, y: Vector3<f32>) {
         self.pos_y = y;
         
@@ -91,45 +118,69 @@ impl Camera {
         );
     }
 
-    pub fn input(
-        &mut self,
-        window: &mut glfw::Window, 
-        glfw: &glfw::Glfw
-    ) {
-        let mut speed = SPEED;
+    // frame time and projection, every frame even while something else moves the camera
+    pub fn frame(&mut self, window: &glfw::Window, glfw: &glfw::Glfw) {
         let curr_frame = glfw.get_time() as f32;
         self.dt = curr_frame - self.last_frame;
         self.last_frame = curr_frame;
 
-        if window.get_key(Key::LeftShift) == Action::Press {
-            speed *= 20.0;
+        let (w, h) = window.get_framebuffer_size();
+        self.aspect = w as f32 / h as f32;
+        self.proj = perspective(Deg(self.fov), self.aspect, self.near, self.far);
+    }
+
+    pub fn input(&mut self, input: &InputState) {
+        if !self.fly {
+            return;
         }
-        if window.get_key(Key::RightShift) == Action::Press {
+
+        let mut speed = self.speed;
+
+        if input.held(InputAction::Sprint) {
             speed *= 20.0;
         }
 
-        if window.get_key(Key::W) == Action::Press {
+        if input.held(InputAction::MoveForward) {
             self.pos_x += speed * self.dt * self.front; 
         }
-        if window.get_key(Key::S) == Action::Press {
+        if input.held(InputAction::MoveBackward) {
             self.pos_x -= speed * self.dt * self.front; 
         }
-        if window.get_key(Key::Space) == Action::Press {
+        if input.held(InputAction::MoveUp) {
             self.pos_x += speed * self.dt * self.up;
         }
-        if window.get_key(Key::LeftControl) == Action::Press {
+        if input.held(InputAction::MoveDown) {
             self.pos_x -= speed * self.dt * self.up;
         }
-        if window.get_key(Key::A) == Action::Press {
+        if input.held(InputAction::MoveLeft) {
             self.pos_x -= speed * self.dt * Vector3::cross(self.front, self.up); 
         }
-        if window.get_key(Key::D) == Action::Press {
+        if input.held(InputAction::MoveRight) {
             self.pos_x += speed * self.dt * Vector3::cross(self.front, self.up); 
         }
+    }
 
-        let (w, h) = window.get_framebuffer_size();
-        let aspect_ratio = w as f32 / h as f32;
-        self.proj = perspective(Deg(70.0), aspect_ratio, 0.1, 1000.0);
+    // movement flattened onto the ground plane, for walking mode
+    pub fn walk_direction(&self, input: &InputState) -> Vector3<f32> {
+        let forward = vec3(self.front.x, 0.0, self.front.z);
+        if forward.magnitude2() == 0.0 {
+            return Vector3::zero();
+        }
+        let forward = forward.normalize();
+        let right = Vector3::cross(forward, UP);
+
+        let mut dir = Vector3::zero();
+        if input.held(InputAction::MoveForward) { dir += forward; }
+        if input.held(InputAction::MoveBackward) { dir -= forward; }
+        if input.held(InputAction::MoveLeft) { dir -= right; }
+        if input.held(InputAction::MoveRight) { dir += right; }
+
+        dir
+    }
+
+    // the next cursor position is taken as is instead of turning the camera
+    pub fn reset_mouse(&mut self) {
+        self.first_mouse = true;
     }
 
     pub fn mouse_callback(
@@ -154,8 +205,8 @@ impl Camera {
         self.last_x = xpos;
         self.last_y = ypos;
 
-        xoffs *= SENSITIVITY;
-        yoffs *= SENSITIVITY;
+        xoffs *= self.sensitivity;
+        yoffs *= self.sensitivity;
 
         self.yaw += xoffs;
         self.pitch += yoffs;
@@ -167,6 +218,25 @@ impl Camera {
             self.pitch = -89.0;
         }
 
+        self.update_direction();
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    // for anything driving the camera other than the mouse
+    pub fn set_orientation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self.update_direction();
+    }
+
+    fn update_direction(&mut self) {
         self.direction.x = Rad::cos(Rad(self.yaw)) * Rad::cos(Rad(self.pitch));
         self.direction.y = Rad::sin(Rad(self.pitch));
         self.direction.z = Rad::sin(Rad(self.yaw)) * Rad::cos(Rad(self.pitch));
@@ -174,18 +244,35 @@ impl Camera {
         self.front = Vector3::normalize(self.direction);
     }
 
+    // world space corners of the part of the view frustum between `near` and `far`,
+    // for fitting something else around it like a shadow cascade
+    pub fn frustum_corners(&self, near: f32, far: f32) -> [Vector3<f32>; 8] {
+        let slice = perspective(Deg(self.fov), self.aspect, near, far) * self.view;
+        let inverse = slice.invert().unwrap_or(Matrix4::identity());
+
+        let mut corners = [Vector3::zero(); 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let ndc = vec4(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 },
+                1.0,
+            );
+            let world = inverse * ndc;
+            *corner = world.truncate() / world.w;
+        }
 
-    // RENDERING //
-    pub unsafe fn send_uniforms(&self, shader: &Shader) {
-        shader.uniform_mat4fv(
-            cstr!("view"),
-            &self.view
-        );
+        corners
+    }
 
-        shader.uniform_mat4fv(
-            cstr!("proj"),
-            &self.proj
-        );
+
+    // RENDERING //
+    // once per frame, every shader with a `Camera` block reads from it. the position is the smoothed
+    // eye the view matrix looks from, so fog and lighting line up with what is drawn
+    pub unsafe fn send_uniforms(&self, block: &UniformBuffer) {
+        block.write(0, &self.proj);
+        block.write(64, &self.view);
+        block.write(128, &self.pos_y.extend(1.0));
     }
 
     pub fn set_projection(
@@ -194,7 +281,7 @@ impl Camera {
     ) {
         match projection_type {
             ProjectionType::Perspective => {
-                self.proj = perspective(Deg(70.0), 1.0, 0.1, 10000.0);
+                self.proj = perspective(Deg(self.fov), 1.0, 0.1, 10000.0);
             },
             ProjectionType::Orthographic => {
                 self.proj = ortho(-1.0, 1.0, -1.0, 1.0, -100.0, 100.0);
@@ -0,0 +1,256 @@
+use std::{fmt::Write as _, fs, io, path::Path, time::Instant};
+
+use cgmath::{Vector3, VectorSpace};
+
+use crate::camera::Camera;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraKeyframe {
+    pub time: f32, // seconds since the recording started
+    pub pos: Vector3<f32>,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+// a recorded flythrough, stored as one `time x y z yaw pitch` line per frame
+#[derive(Clone, Debug, Default)]
+pub struct CameraPath {
+    pub keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = String::from("# time x y z yaw pitch\n");
+        for k in &self.keyframes {
+            let _ = writeln!(out, "{} {} {} {} {} {}", k.time, k.pos.x, k.pos.y, k.pos.z, k.yaw, k.pitch);
+        }
+
+        fs::write(path, out)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut keyframes: Vec<CameraKeyframe> = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let values = line.split_whitespace()
+                .map(str::parse::<f32>)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("line {}: {e}", i + 1))?;
+            let [time, x, y, z, yaw, pitch] = values[..] else {
+                return Err(format!("line {}: expected 6 values, got {}", i + 1, values.len()));
+            };
+
+            if keyframes.last().is_some_and(|k| k.time > time) {
+                return Err(format!("line {}: time goes backwards", i + 1));
+            }
+
+            keyframes.push(CameraKeyframe { time, pos: Vector3::new(x, y, z), yaw, pitch });
+        }
+
+        Ok(Self { keyframes })
+    }
+
+    // linearly interpolated between the surrounding keyframes, clamped at both ends
+    pub fn sample(&self, time: f32) -> Option<CameraKeyframe> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+        if time <= first.time {
+            return Some(*first);
+        }
+        if time >= last.time {
+            return Some(*last);
+        }
+
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let (a, b) = (self.keyframes[next - 1], self.keyframes[next]);
+        let span = b.time - a.time;
+        let t = if span > 0.0 { (time - a.time) / span } else { 0.0 };
+
+        Some(CameraKeyframe {
+            time,
+            pos: a.pos.lerp(b.pos, t),
+            yaw: a.yaw + (b.yaw - a.yaw) * t,
+            pitch: a.pitch + (b.pitch - a.pitch) * t,
+        })
+    }
+}
+
+pub struct PathRecorder {
+    path: CameraPath,
+    start: Instant,
+}
+
+impl Default for PathRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PathRecorder {
+    pub fn new() -> Self {
+        Self {
+            path: CameraPath::default(),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn record(&mut self, camera: &Camera) {
+        self.path.keyframes.push(CameraKeyframe {
+            time: self.start.elapsed().as_secs_f32(),
+            pos: camera.pos_x,
+            yaw: camera.yaw(),
+            pitch: camera.pitch(),
+        });
+    }
+
+    pub fn finish(self) -> CameraPath {
+        self.path
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PlaybackFrame {
+    pub time: f32,
+    pub frame_time: f32,
+    pub chunks_loaded: usize,
+    pub chunks_generated: usize,
+}
+
+// drives the camera along a recorded path and keeps per-frame numbers so runs can be compared
+pub struct PathPlayer {
+    path: CameraPath,
+    start: Instant,
+    frames: Vec<PlaybackFrame>,
+}
+
+impl PathPlayer {
+    pub fn new(path: CameraPath) -> Self {
+        Self {
+            path,
+            start: Instant::now(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.start.elapsed().as_secs_f32() > self.path.duration()
+    }
+
+    // moves the camera to where the path is now, returns false once the path has ended
+    pub fn apply(&mut self, camera: &mut Camera) -> bool {
+        let Some(k) = self.path.sample(self.start.elapsed().as_secs_f32()) else {
+            return false;
+        };
+
+        camera.pos_x = k.pos;
+        camera.set_orientation(k.yaw, k.pitch);
+
+        !self.is_finished()
+    }
+
+    pub fn record_frame(&mut self, frame_time: f32, chunks_loaded: usize, chunks_generated: usize) {
+        self.frames.push(PlaybackFrame {
+            time: self.start.elapsed().as_secs_f32(),
+            frame_time,
+            chunks_loaded,
+            chunks_generated,
+        });
+    }
+
+    pub fn summary(&self) -> String {
+        if self.frames.is_empty() {
+            return "no frames played".to_string();
+        }
+
+        let mut times: Vec<f32> = self.frames.iter().map(|f| f.frame_time).collect();
+        times.sort_by(f32::total_cmp);
+        let avg = times.iter().sum::<f32>() / times.len() as f32;
+        let p99 = times[((times.len() - 1) as f32 * 0.99) as usize];
+        let max = times[times.len() - 1];
+        let generated = self.frames.last().map_or(0, |f| f.chunks_generated)
+            - self.frames.first().map_or(0, |f| f.chunks_generated);
+
+        format!(
+            "{} frames, frame time avg {:.2}ms p99 {:.2}ms max {:.2}ms, {} chunks generated",
+            times.len(), avg * 1000.0, p99 * 1000.0, max * 1000.0, generated,
+        )
+    }
+
+    pub fn save_frames(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = String::from("time,frame_time,chunks_loaded,chunks_generated\n");
+        for f in &self.frames {
+            let _ = writeln!(out, "{},{},{},{}", f.time, f.frame_time, f.chunks_loaded, f.chunks_generated);
+        }
+
+        fs::write(path, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f32, x: f32, yaw: f32) -> CameraKeyframe {
+        CameraKeyframe { time, pos: Vector3::new(x, 2.0, -3.0), yaw, pitch: 0.5 }
+    }
+
+    #[test]
+    fn parses_keyframes() {
+        let path = CameraPath::parse("# time x y z yaw pitch\n\n0 1 2 -3 10 0.5\n  0.5 4 2 -3 20 0.5  \n0.5 5 2 -3 30 0.5\n").unwrap();
+        assert_eq!(path.keyframes, vec![keyframe(0.0, 1.0, 10.0), keyframe(0.5, 4.0, 20.0), keyframe(0.5, 5.0, 30.0)]);
+        assert_eq!(path.duration(), 0.5);
+        assert!(CameraPath::parse("").unwrap().keyframes.is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        let cases = [
+            ("0 1 2 3 4 five", "line 1: invalid float"),
+            ("0 1 2 3 4", "line 1: expected 6 values, got 5"),
+            ("0 1 2 3 4 5 6", "line 1: expected 6 values, got 7"),
+            ("# header\n1 0 0 0 0 0\n0.5 0 0 0 0 0", "line 3: time goes backwards"),
+        ];
+
+        for (text, error) in cases {
+            let result = CameraPath::parse(text);
+            assert!(result.as_ref().is_err_and(|e| e.starts_with(error)), "{text:?}: {result:?}");
+        }
+    }
+
+    #[test]
+    fn saved_paths_load_back() {
+        let path = CameraPath { keyframes: vec![keyframe(0.0, 1.25, -90.0), keyframe(1.0 / 3.0, 1e-7, 359.9)] };
+        let file = std::env::temp_dir().join(format!("camera_path_test_{}.txt", std::process::id()));
+        path.save(&file).unwrap();
+        let loaded = CameraPath::load(&file);
+        let _ = fs::remove_file(&file);
+
+        assert_eq!(loaded.unwrap().keyframes, path.keyframes);
+    }
+
+    #[test]
+    fn samples_between_keyframes() {
+        let path = CameraPath { keyframes: vec![keyframe(1.0, 0.0, 0.0), keyframe(3.0, 10.0, 90.0)] };
+        assert_eq!(path.sample(0.0), Some(path.keyframes[0]));
+        assert_eq!(path.sample(5.0), Some(path.keyframes[1]));
+
+        let middle = path.sample(2.0).unwrap();
+        assert_eq!(middle.pos.x, 5.0);
+        assert_eq!(middle.yaw, 45.0);
+        assert_eq!(CameraPath::default().sample(0.0), None);
+    }
+}
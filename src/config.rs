@@ -0,0 +1,105 @@
+use std::fmt;
+
+// tiny `key = value` format shared by the engine's config files.
+// `#` starts a comment, blank lines are ignored and keys may repeat
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigEntry {
+    pub line: usize,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ConfigError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self { line, message: message.into() }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+pub fn parse_config(text: &str) -> Result<Vec<ConfigEntry>, ConfigError> {
+    let mut entries = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        entries.extend(parse_line(i + 1, raw)?);
+    }
+
+    Ok(entries)
+}
+
+// one line of a config file, `None` for blank lines and comments. for files where a bad line
+// shouldn't cost the good ones
+pub fn parse_line(line: usize, raw: &str) -> Result<Option<ConfigEntry>, ConfigError> {
+    let content = raw.split('#').next().unwrap_or("").trim();
+    if content.is_empty() {
+        return Ok(None);
+    }
+
+    let Some((key, value)) = content.split_once('=') else {
+        return Err(ConfigError::new(line, format!("expected `key = value`, got `{content}`")));
+    };
+
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(ConfigError::new(line, "missing key before `=`"));
+    }
+
+    Ok(Some(ConfigEntry {
+        line,
+        key: key.to_lowercase(),
+        value: value.trim().to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(line: usize, key: &str, value: &str) -> ConfigEntry {
+        ConfigEntry { line, key: key.to_string(), value: value.to_string() }
+    }
+
+    #[test]
+    fn parses_entries() {
+        let cases: &[(&str, Vec<ConfigEntry>)] = &[
+            ("", vec![]),
+            ("a = 1", vec![entry(1, "a", "1")]),
+            ("  Key=  some value  ", vec![entry(1, "key", "some value")]),
+            ("# only a comment\n\n   \n", vec![]),
+            ("a = 1 # trailing comment", vec![entry(1, "a", "1")]),
+            ("\n\na = 1\n# b = 2\nc = 3", vec![entry(3, "a", "1"), entry(5, "c", "3")]),
+            ("a = 1\na = 2", vec![entry(1, "a", "1"), entry(2, "a", "2")]),
+            ("a =", vec![entry(1, "a", "")]),
+            ("a = b = c", vec![entry(1, "a", "b = c")]),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(&parse_config(text).unwrap(), expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let cases = [
+            ("no equals sign", 1),
+            ("a = 1\njust words", 2),
+            ("= value", 1),
+            ("a = 1\n\n   = 2 # comment", 3),
+        ];
+
+        for (text, line) in cases {
+            let error = parse_config(text).unwrap_err();
+            assert_eq!(error.line, line, "{text:?}");
+        }
+    }
+}
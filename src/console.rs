@@ -0,0 +1,39 @@
+use std::{io::BufRead, sync::mpsc::{channel, Receiver, TryRecvError}, thread};
+
+// commands typed into the terminal the game was started from, read on a background thread
+// so the render loop never blocks on stdin
+pub struct Console {
+    lines: Receiver<String>,
+}
+
+impl Console {
+    pub fn spawn() -> Self {
+        let (sender, lines) = channel();
+
+        thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break; // game has shut down
+                }
+            }
+        });
+
+        Self { lines }
+    }
+
+    // the next command waiting, split into words. empty lines are skipped
+    pub fn poll(&self) -> Option<Vec<String>> {
+        loop {
+            match self.lines.try_recv() {
+                Ok(line) => {
+                    let words: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+                    if !words.is_empty() {
+                        return Some(words);
+                    }
+                },
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::{ffi::c_void, mem::{size_of, size_of_val}};
+
+use cgmath::Vector3;
+use gl::*;
+use gl::types::*;
+
+use crate::{glsl::Defines, shader::ShaderError, shader_watcher::ShaderWatcher, shaders::*, world::RaycastHit};
+
+const ARM_LENGTH: f32 = 10.0; // pixels from the centre
+const THICKNESS: f32 = 2.0; // pixels
+const OUTLINE_GROW: f32 = 0.002; // keeps the outline out of the block's own faces
+const FACE_OFFSET: f32 = 0.003;
+
+// screen centre crosshair plus the outline of whatever block it's pointing at
+pub struct Crosshair {
+    crosshair_shader: ShaderWatcher,
+    outline_shader: ShaderWatcher,
+    vao_2d: u32,
+    vbo_2d: u32,
+    vao_3d: u32,
+    vbo_3d: u32,
+}
+
+impl Crosshair {
+    pub unsafe fn new() -> Result<Self, ShaderError> {
+        let crosshair_shader = ShaderWatcher::new("metrics", METRICS_SHADER_VS, METRICS_SHADER_FS, Defines::new())?;
+        let outline_shader = ShaderWatcher::new("outline", OUTLINE_SHADER_VS, OUTLINE_SHADER_FS, Defines::new())?;
+
+        let (mut vao_2d, mut vbo_2d, mut vao_3d, mut vbo_3d) = (0, 0, 0, 0);
+        for (vao, vbo, components) in [(&mut vao_2d, &mut vbo_2d, 2), (&mut vao_3d, &mut vbo_3d, 3)] {
+            GenVertexArrays(1, vao);
+            GenBuffers(1, vbo);
+            BindVertexArray(*vao);
+            BindBuffer(ARRAY_BUFFER, *vbo);
+            EnableVertexAttribArray(0);
+            VertexAttribPointer(0, components, FLOAT, FALSE, components * size_of::<f32>() as GLsizei, std::ptr::null());
+        }
+        BindVertexArray(0);
+
+        Ok(Self {
+            crosshair_shader,
+            outline_shader,
+            vao_2d,
+            vbo_2d,
+            vao_3d,
+            vbo_3d,
+        })
+    }
+
+    pub fn shaders(&mut self) -> [&mut ShaderWatcher; 2] {
+        [&mut self.crosshair_shader, &mut self.outline_shader]
+    }
+
+    unsafe fn upload<T>(vbo: u32, vertices: &[T]) {
+        BindBuffer(ARRAY_BUFFER, vbo);
+        BufferData(
+            ARRAY_BUFFER,
+            size_of_val(vertices) as GLsizeiptr,
+            vertices.as_ptr() as *const c_void,
+            STREAM_DRAW,
+        );
+    }
+
+    // the 12 edges of the unit cube at `voxel`, grown slightly so they don't z-fight
+    fn outline_edges(voxel: Vector3<i32>) -> Vec<[f32; 3]> {
+        let min = voxel.map(|v| v as f32 - OUTLINE_GROW);
+        let max = voxel.map(|v| v as f32 + 1.0 + OUTLINE_GROW);
+        let corner = |i: usize| [
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        ];
+
+        // corners that differ in exactly one bit share an edge
+        (0..8usize)
+            .flat_map(|a| [1, 2, 4].into_iter().map(move |bit| (a, a | bit)))
+            .filter(|(a, b)| a != b)
+            .flat_map(|(a, b)| [corner(a), corner(b)])
+            .collect()
+    }
+
+    // the face the ray entered through, pushed just off the block
+    fn hit_face(hit: &RaycastHit) -> Option<[[f32; 3]; 6]> {
+        let axis = (0..3).find(|a| hit.normal[*a] != 0)?;
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let plane = hit.voxel[axis] as f32
+            + if hit.normal[axis] > 0 { 1.0 + FACE_OFFSET } else { -FACE_OFFSET };
+
+        let point = |du: f32, dv: f32| {
+            let mut p = [0.0; 3];
+            p[axis] = plane;
+            p[u] = hit.voxel[u] as f32 + du;
+            p[v] = hit.voxel[v] as f32 + dv;
+            p
+        };
+
+        Some([point(0.0, 0.0), point(1.0, 0.0), point(1.0, 1.0), point(0.0, 0.0), point(1.0, 1.0), point(0.0, 1.0)])
+    }
+
+    pub unsafe fn draw_outline(&self, hit: &RaycastHit) {
+        self.outline_shader.shader.use_shader();
+        BindVertexArray(self.vao_3d);
+
+        let edges = Self::outline_edges(hit.voxel);
+        Self::upload(self.vbo_3d, &edges);
+        self.outline_shader.shader.set_uniform("color", [0.0, 0.0, 0.0, 1.0]);
+        DrawArrays(LINES, 0, edges.len() as GLsizei);
+
+        if let Some(face) = Self::hit_face(hit) {
+            Enable(BLEND);
+            BlendFunc(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+            DepthMask(FALSE);
+            Self::upload(self.vbo_3d, &face);
+            self.outline_shader.shader.set_uniform("color", [1.0, 1.0, 1.0, 0.25]);
+            DrawArrays(TRIANGLES, 0, face.len() as GLsizei);
+            DepthMask(TRUE);
+            Disable(BLEND);
+        }
+
+        BindVertexArray(0);
+    }
+
+    pub unsafe fn draw(&self, window_width: i32, window_height: i32) {
+        // pixel sizes in ndc, so the crosshair stays square whatever the aspect
+        let (px, py) = (2.0 / window_width as f32, 2.0 / window_height as f32);
+        let (arm_x, arm_y) = (ARM_LENGTH * px, ARM_LENGTH * py);
+        let (half_x, half_y) = (THICKNESS * 0.5 * px, THICKNESS * 0.5 * py);
+
+        let rect = |x0: f32, y0: f32, x1: f32, y1: f32| [[x0, y0], [x1, y0], [x1, y1], [x0, y0], [x1, y1], [x0, y1]];
+        let vertices: Vec<[f32; 2]> = rect(-arm_x, -half_y, arm_x, half_y).into_iter()
+            .chain(rect(-half_x, -arm_y, half_x, -half_y))
+            .chain(rect(-half_x, half_y, half_x, arm_y))
+            .collect();
+
+        Disable(DEPTH_TEST);
+        // inverts whatever is behind it so it's visible on both sky and terrain
+        Enable(BLEND);
+        BlendFunc(ONE_MINUS_DST_COLOR, ZERO);
+
+        self.crosshair_shader.shader.use_shader();
+        self.crosshair_shader.shader.set_uniform("color", [1.0, 1.0, 1.0, 1.0]);
+        BindVertexArray(self.vao_2d);
+        Self::upload(self.vbo_2d, &vertices);
+        DrawArrays(TRIANGLES, 0, vertices.len() as GLsizei);
+        BindVertexArray(0);
+
+        Disable(BLEND);
+        Enable(DEPTH_TEST);
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt::Write as _;
+
+use cgmath::{Vector3, vec3};
+
+use crate::{camera::Camera, text::TextRenderer, time_of_day::WorldClock, world::{world_to_chunk, World, REACH}};
+
+const TEXT_SCALE: f32 = 2.0;
+const MARGIN: f32 = 8.0; // pixels from the top left of the window
+
+// f3 style readout of where the camera is and what it's looking at
+pub struct DebugHud {
+    pub visible: bool,
+    fps: f32, // smoothed, the raw value flickers too much to read
+}
+
+impl Default for DebugHud {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugHud {
+    pub fn new() -> Self {
+        Self {
+            visible: true,
+            fps: 0.0,
+        }
+    }
+
+    fn facing(front: Vector3<f32>) -> &'static str {
+        if front.x.abs() > front.z.abs() {
+            if front.x > 0.0 { "east (+x)" } else { "west (-x)" }
+        } else if front.z > 0.0 { "south (+z)" } else { "north (-z)" }
+    }
+
+    pub fn text(&mut self, camera: &Camera, world: &World, clock: &WorldClock) -> String {
+        if camera.dt > 0.0 {
+            self.fps += (1.0 / camera.dt - self.fps) * 0.05;
+        }
+
+        let pos = camera.pos_x;
+        let voxel = vec3(pos.x.floor() as i32, pos.y.floor() as i32, pos.z.floor() as i32);
+        let (chunk, (lx, ly, lz)) = world_to_chunk(voxel);
+
+        let mut out = String::new();
+        let _ = writeln!(out, "{:.0} fps ({:.2}ms)", self.fps, camera.dt * 1000.0);
+        let _ = writeln!(out, "xyz: {:.2} / {:.2} / {:.2}", pos.x, pos.y, pos.z);
+        let _ = writeln!(out, "chunk: {} {} {} in {} {} {}", lx, ly, lz, chunk.x, chunk.y, chunk.z);
+        let _ = writeln!(
+            out, "facing: {} yaw {:.1} pitch {:.1}",
+            Self::facing(camera.front), camera.yaw(), camera.pitch(),
+        );
+        match world.raycast(pos, camera.front, REACH) {
+            Some(hit) => {
+                let _ = writeln!(
+                    out, "looking at: id {} at {} {} {} ({:.1}m)",
+                    hit.id, hit.voxel.x, hit.voxel.y, hit.voxel.z, hit.distance,
+                );
+            },
+            None => {
+                let _ = writeln!(out, "looking at: nothing");
+            },
+        }
+        let _ = writeln!(out, "chunks: {} loaded, {} generated", world.chunks.len(), world.chunks_generated);
+        let _ = writeln!(out, "time: {}", clock.describe());
+
+        out
+    }
+
+    pub fn draw(&mut self, camera: &Camera, world: &World, clock: &WorldClock, text: &mut TextRenderer) {
+        if !self.visible {
+            return;
+        }
+
+        let lines = self.text(camera, world, clock);
+        text.queue_shadowed(&lines, MARGIN, MARGIN, TEXT_SCALE, [1.0, 1.0, 1.0, 1.0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{block::GROUND, terrain::TerrainGenerator, world::Voxel};
+
+    struct Empty;
+
+    impl TerrainGenerator for Empty {
+        fn generate(&self, _chunk_pos: Vector3<f32>, _voxels: &mut [Voxel], _biomes: &mut [u8]) {}
+    }
+
+    #[test]
+    fn describes_the_camera_and_its_target() {
+        let mut world = World::headless(Arc::new(Empty));
+        world.set_voxel(Vector3::new(6, 5, 3), Voxel::ground());
+        let clock = WorldClock::new(600.0, 6.0);
+        let mut camera = Camera::new(); // looking down +x
+        camera.pos_x = vec3(2.5, 5.5, 3.5);
+        camera.dt = 0.02;
+
+        let mut hud = DebugHud::new();
+        let text = hud.text(&camera, &world, &clock);
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines[1..], [
+            "xyz: 2.50 / 5.50 / 3.50",
+            "chunk: 2 5 3 in 0 0 0",
+            "facing: east (+x) yaw 0.0 pitch 0.0",
+            &format!("looking at: id {GROUND} at 6 5 3 (3.5m)"),
+            "chunks: 1 loaded, 0 generated",
+            "time: day 1 06:00",
+        ]);
+        // eased towards 50 fps
+        assert!(lines[0].starts_with("2 fps (20.00ms)"), "{}", lines[0]);
+
+        // negative positions land in the chunk below, counted from its low corner
+        camera.pos_x = vec3(-0.5, 5.5, -24.5);
+        camera.set_orientation(-std::f32::consts::FRAC_PI_2, 0.0);
+        let text = hud.text(&camera, &world, &clock);
+        assert!(text.contains("chunk: 23 5 23 in -1 0 -2\n"), "{text}");
+        assert!(text.contains("facing: north (-z)"), "{text}");
+        assert!(text.contains("looking at: nothing\n"), "{text}");
+    }
+}
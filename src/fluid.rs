@@ -0,0 +1,197 @@
+use cgmath::Vector3;
+
+use crate::{block::{self, Flow, AIR}, world::{Voxel, World}};
+
+const HORIZONTAL: [Vector3<i32>; 4] = [
+    Vector3::new(-1, 0, 0),
+    Vector3::new(1, 0, 0),
+    Vector3::new(0, 0, -1),
+    Vector3::new(0, 0, 1),
+];
+const DOWN: Vector3<i32> = Vector3::new(0, -1, 0);
+const UP: Vector3<i32> = Vector3::new(0, 1, 0);
+
+// the height of a fluid's surface in 16ths of a block. sources sit a little below the top,
+// flowing fluid gets lower the further it is from one
+pub fn surface_height(voxel: &Voxel) -> u32 {
+    let Some(flow) = block::block(voxel.id()).flow() else {
+        return 16;
+    };
+    if voxel.is_falling() {
+        return 16;
+    }
+
+    14 - voxel.level() as u32 * 12 / flow.reach as u32
+}
+
+// one step of a water or lava cell, run by `BlockUpdates` whenever it or a neighbour changed.
+// everything goes through world positions, so chunk borders don't matter, and fluid stops at
+// chunks that aren't loaded. fluids only flow into air, water and lava don't mix
+pub fn update(world: &mut World, pos: Vector3<i32>, voxel: Voxel, flow: Flow) {
+    let id = voxel.id();
+
+    // sources stay put, flowing fluid takes whatever level its neighbours give it
+    let voxel = if voxel.is_source() {
+        voxel
+    } else {
+        match fed_state(world, pos, id, flow) {
+            Some(fed) => {
+                if fed != voxel {
+                    world.set_voxel(pos, fed);
+                }
+                fed
+            },
+            None => {
+                world.set_voxel(pos, Voxel::air());
+                return;
+            },
+        }
+    };
+
+    // down first, it only spreads sideways once it lands on something
+    match world.voxel_at(pos + DOWN) {
+        Some(below) if below.id() == AIR => {
+            world.set_voxel(pos + DOWN, Voxel::fluid(id, 0, true));
+            return;
+        },
+        Some(below) if below.id() == id => return,
+        _ => {},
+    }
+
+    let level = spread_level(&voxel);
+    if level > flow.reach {
+        return;
+    }
+    for offset in HORIZONTAL {
+        let replace = match world.voxel_at(pos + offset) {
+            Some(next) if next.id() == AIR => true,
+            Some(next) if next.id() == id => !next.is_source() && !next.is_falling() && next.level() > level,
+            _ => false,
+        };
+        if replace {
+            world.set_voxel(pos + offset, Voxel::fluid(id, level, false));
+        }
+    }
+}
+
+// the level fluid flowing out of `voxel` sideways gets
+fn spread_level(voxel: &Voxel) -> u8 {
+    if voxel.is_falling() { 1 } else { voxel.level() + 1 }
+}
+
+// what a flowing cell should be given the fluid around it, `None` once nothing feeds it
+fn fed_state(world: &World, pos: Vector3<i32>, id: u8, flow: Flow) -> Option<Voxel> {
+    if world.voxel_at(pos + UP).is_some_and(|above| above.id() == id) {
+        return Some(Voxel::fluid(id, 0, true));
+    }
+
+    let level = HORIZONTAL
+        .into_iter()
+        .filter(|offset| spreads_sideways(world, pos + offset, id))
+        .filter_map(|offset| world.voxel_at(pos + offset).map(spread_level))
+        .min()?;
+
+    (level <= flow.reach).then(|| Voxel::fluid(id, level, false))
+}
+
+// whether the fluid at `pos` is `id` and resting on something, so it flows out to the sides
+fn spreads_sideways(world: &World, pos: Vector3<i32>, id: u8) -> bool {
+    if world.voxel_at(pos).is_none_or(|voxel| voxel.id() != id) {
+        return false;
+    }
+
+    match world.voxel_at(pos + DOWN) {
+        Some(below) => below.id() != AIR && below.id() != id,
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{block::WATER, block_updates::BlockUpdates, integrity::Detached, terrain::{voxel_index, TerrainGenerator}, world::{Chunk, CHUNK_SIZE}};
+
+    // ground at y = 0 in every chunk at chunk height 0, air everywhere else
+    struct Floor;
+
+    impl TerrainGenerator for Floor {
+        fn generate(&self, chunk_pos: Vector3<f32>, voxels: &mut [Voxel], _biomes: &mut [u8]) {
+            if chunk_pos.y != 0.0 {
+                return;
+            }
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    voxels[voxel_index(x, 0, z)] = Voxel::ground();
+                }
+            }
+        }
+    }
+
+    // the chunk at the origin, plus one to its +x
+    fn world() -> World {
+        let mut world = World::headless(Arc::new(Floor));
+        world.chunks.insert(Vector3::new(1, 0, 0), Chunk::with_generator(Vector3::new(1.0, 0.0, 0.0), &Floor));
+        world
+    }
+
+    fn settle(world: &mut World, updates: &mut BlockUpdates) {
+        for _ in 0..400 {
+            updates.update(world, 0.05);
+        }
+        assert_eq!(updates.pending(), 0, "still flowing");
+    }
+
+    fn water_at(world: &World, x: i32, z: i32) -> Option<Voxel> {
+        world.voxel_at(Vector3::new(x, 1, z)).copied().filter(|voxel| voxel.id() == WATER)
+    }
+
+    #[test]
+    fn spreads_as_far_as_its_reach() {
+        let reach = block::block(WATER).flow().unwrap().reach as i32;
+        let (mut world, mut updates) = (world(), BlockUpdates::new(Detached::Off));
+        world.set_voxel(Vector3::new(12, 1, 12), Voxel::fluid(WATER, 0, false));
+        settle(&mut world, &mut updates);
+
+        for distance in 1..=reach {
+            let flowing = water_at(&world, 12 - distance, 12).unwrap();
+            assert_eq!(flowing.level(), distance as u8);
+            assert!(!flowing.is_source());
+        }
+        assert!(water_at(&world, 12 - reach - 1, 12).is_none());
+        // diagonal cells are reached around the corner, one level per step
+        assert_eq!(water_at(&world, 9, 9).map(|voxel| voxel.level()), Some(6));
+        assert!(water_at(&world, 8, 8).is_none());
+    }
+
+    #[test]
+    fn drains_once_the_source_is_gone() {
+        let (mut world, mut updates) = (world(), BlockUpdates::new(Detached::Off));
+        world.set_voxel(Vector3::new(12, 1, 12), Voxel::fluid(WATER, 0, false));
+        settle(&mut world, &mut updates);
+        assert!(water_at(&world, 10, 12).is_some());
+
+        world.set_voxel(Vector3::new(12, 1, 12), Voxel::air());
+        settle(&mut world, &mut updates);
+        for x in 0..CHUNK_SIZE as i32 * 2 {
+            for z in 0..CHUNK_SIZE as i32 {
+                assert!(water_at(&world, x, z).is_none(), "water left at {x} {z}");
+            }
+        }
+    }
+
+    #[test]
+    fn flows_across_chunk_borders() {
+        let (mut world, mut updates) = (world(), BlockUpdates::new(Detached::Off));
+        // poured from above, it falls onto the floor and spreads from there
+        world.set_voxel(Vector3::new(22, 5, 12), Voxel::fluid(WATER, 0, false));
+        settle(&mut world, &mut updates);
+
+        assert!(world.voxel_at(Vector3::new(22, 3, 12)).is_some_and(|voxel| voxel.id() == WATER && voxel.is_falling()));
+        assert_eq!(water_at(&world, 22, 12).map(|voxel| voxel.is_falling()), Some(true));
+        assert_eq!(water_at(&world, 24, 12).map(|voxel| voxel.level()), Some(2));
+        assert_eq!(water_at(&world, 29, 12).map(|voxel| voxel.level()), Some(7));
+        assert!(water_at(&world, 30, 12).is_none());
+    }
+}
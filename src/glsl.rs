@@ -0,0 +1,267 @@
+use std::{collections::{BTreeMap, HashMap}, fmt, fs, path::Path};
+
+use crate::{shader::{Shader, ShaderError}, shaders::{INCLUDES, SHADER_DIR}};
+
+// injected after `#version` as `#define NAME VALUE`, sorted so the same set always expands the same
+pub type Defines = BTreeMap<String, String>;
+
+// defines that are only switched on or off, `#ifdef FOG`
+pub fn flags(names: &[&str]) -> Defines {
+    names.iter().map(|name| (name.to_string(), String::new())).collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreprocessError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+// an include from `shaders/` on disk so edits get picked up on reload, otherwise the embedded copy
+pub fn load_include(name: &str) -> Option<String> {
+    fs::read_to_string(Path::new(SHADER_DIR).join(name)).ok()
+        .or_else(|| INCLUDES.iter().find(|(n, _)| *n == name).map(|(_, code)| code.to_string()))
+}
+
+// a shader after preprocessing, with every file that went into it
+#[derive(Clone, Debug, Default)]
+pub struct Expanded {
+    pub code: String,
+    pub files: Vec<(String, String)>, // name and unexpanded source, indexed by the `#line` source string
+}
+
+// expands `#include "file"` and injects `defines` straight after `#version`.
+// `#line` directives keep driver errors pointing at lines of the file they came from:
+// source string 0 is `source` itself, includes are numbered in the order they're first reached
+pub fn preprocess(
+    source: &str,
+    defines: &Defines,
+    include: &dyn Fn(&str) -> Option<String>,
+) -> Result<Expanded, PreprocessError> {
+    let mut out = String::new();
+    let mut files = vec![("<main>".to_string(), source.to_string())];
+    let mut lines = source.lines().enumerate();
+
+    // `#version` has to come before anything else, defines included
+    let has_version = source.lines()
+        .find(|l| !l.trim().is_empty())
+        .is_some_and(|l| l.trim_start().starts_with("#version"));
+    if has_version {
+        for (_, line) in lines.by_ref() {
+            out.push_str(line);
+            out.push('\n');
+            if line.trim_start().starts_with("#version") {
+                break;
+            }
+        }
+    }
+
+    for (name, value) in defines {
+        match value.is_empty() {
+            true => out.push_str(&format!("#define {name}\n")),
+            false => out.push_str(&format!("#define {name} {value}\n")),
+        }
+    }
+
+    let rest: Vec<(usize, &str)> = lines.collect();
+    expand(&rest, 0, include, &mut files, &mut vec!["<main>".to_string()], &mut out)?;
+
+    Ok(Expanded { code: out, files })
+}
+
+fn expand(
+    lines: &[(usize, &str)],
+    file_id: usize,
+    include: &dyn Fn(&str) -> Option<String>,
+    files: &mut Vec<(String, String)>,
+    stack: &mut Vec<String>,
+    out: &mut String,
+) -> Result<(), PreprocessError> {
+    if let Some((first, _)) = lines.first() {
+        out.push_str(&format!("#line {} {file_id}\n", first + 1));
+    }
+
+    for (i, line) in lines {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("#version") && file_id != 0 {
+            return Err(error(stack, *i, "#version in an included file".to_string()));
+        }
+
+        let Some(directive) = trimmed.strip_prefix("#include") else {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        };
+
+        let name = directive.trim()
+            .strip_prefix('"')
+            .and_then(|d| d.strip_suffix('"'))
+            .ok_or_else(|| error(stack, *i, format!("expected #include \"file\", got `{trimmed}`")))?;
+        if stack.iter().any(|s| s == name) {
+            return Err(error(stack, *i, format!("{name} includes itself via {}", stack.join(" -> "))));
+        }
+        let code = include(name).ok_or_else(|| error(stack, *i, format!("no such include {name}")))?;
+
+        let id = match files.iter().position(|(f, _)| f == name) {
+            Some(id) => id,
+            None => {
+                files.push((name.to_string(), code.clone()));
+                files.len() - 1
+            },
+        };
+
+        out.push_str(&format!("// {name}\n"));
+        stack.push(name.to_string());
+        let included: Vec<(usize, &str)> = code.lines().enumerate().collect();
+        expand(&included, id, include, files, stack, out)?;
+        stack.pop();
+
+        // back to the line after the include
+        out.push_str(&format!("#line {} {file_id}\n", i + 2));
+    }
+
+    Ok(())
+}
+
+fn error(stack: &[String], line: usize, message: String) -> PreprocessError {
+    PreprocessError {
+        file: stack.last().cloned().unwrap_or_default(),
+        line: line + 1,
+        message,
+    }
+}
+
+// one program per set of defines, built from the same sources on first use
+pub struct ShaderVariants {
+    vs_source: String,
+    fs_source: String,
+    programs: HashMap<Defines, Shader>,
+}
+
+impl ShaderVariants {
+    pub fn new(vs_source: &str, fs_source: &str) -> Self {
+        Self {
+            vs_source: vs_source.to_string(),
+            fs_source: fs_source.to_string(),
+            programs: HashMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, defines: &Defines) -> Result<Shader, ShaderError> {
+        if let Some(shader) = self.programs.get(defines) {
+            return Ok(shader.clone());
+        }
+
+        let shader = Shader::with_defines(&self.vs_source, &self.fs_source, defines)?;
+        self.programs.insert(defines.clone(), shader.clone());
+
+        Ok(shader)
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+
+    pub unsafe fn delete(&mut self) {
+        for shader in self.programs.values() {
+            shader.delete();
+        }
+        self.programs.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn include(name: &str) -> Option<String> {
+        let code = match name {
+            "common.glsl" => "float a;\n#include \"inner.glsl\"\nfloat b;",
+            "inner.glsl" => "float c;",
+            "self.glsl" => "#include \"self.glsl\"",
+            "ping.glsl" => "float d;\n#include \"pong.glsl\"",
+            "pong.glsl" => "#include \"ping.glsl\"",
+            "versioned.glsl" => "#version 330 core",
+            _ => return None,
+        };
+
+        Some(code.to_string())
+    }
+
+    fn expand(source: &str) -> Result<Expanded, PreprocessError> {
+        preprocess(source, &flags(&["FOG"]), &include)
+    }
+
+    #[test]
+    fn defines_go_after_version() {
+        let mut defines = flags(&["FOG"]);
+        defines.insert("DENSITY".to_string(), "0.02".to_string());
+
+        let expanded = preprocess("\n#version 330 core\nvoid main() {}", &defines, &include).unwrap();
+        assert_eq!(expanded.code, "\n#version 330 core\n#define DENSITY 0.02\n#define FOG\n#line 3 0\nvoid main() {}\n");
+
+        // without a version there's nothing to wait for
+        assert_eq!(expand("void main() {}").unwrap().code, "#define FOG\n#line 1 0\nvoid main() {}\n");
+    }
+
+    #[test]
+    fn includes_are_numbered_and_lines_restored() {
+        let source = "#version 330 core\n#include \"common.glsl\"\n#include \"inner.glsl\"\nvoid main() {}";
+        let expanded = expand(source).unwrap();
+
+        assert_eq!(expanded.code, [
+            "#version 330 core",
+            "#define FOG",
+            "#line 2 0",
+            "// common.glsl",
+            "#line 1 1",
+            "float a;",
+            "// inner.glsl",
+            "#line 1 2",
+            "float c;",
+            "#line 3 1",
+            "float b;",
+            "#line 3 0",
+            "// inner.glsl",
+            "#line 1 2",
+            "float c;",
+            "#line 4 0",
+            "void main() {}",
+            "",
+        ].join("\n"));
+
+        let names: Vec<&str> = expanded.files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["<main>", "common.glsl", "inner.glsl"]);
+        assert_eq!(expanded.files[0].1, source);
+        assert_eq!(expanded.files[2].1, "float c;");
+    }
+
+    #[test]
+    fn reports_where_includes_go_wrong() {
+        let cases = [
+            ("#include \"self.glsl\"", "self.glsl", 1, "self.glsl includes itself via <main> -> self.glsl"),
+            ("\n#include \"ping.glsl\"", "pong.glsl", 1, "ping.glsl includes itself via <main> -> ping.glsl -> pong.glsl"),
+            ("float a;\n#include \"missing.glsl\"", "<main>", 2, "no such include missing.glsl"),
+            ("#include <common.glsl>", "<main>", 1, "expected #include \"file\", got `#include <common.glsl>`"),
+            ("#include \"versioned.glsl\"", "versioned.glsl", 1, "#version in an included file"),
+        ];
+
+        for (source, file, line, message) in cases {
+            let error = expand(source).unwrap_err();
+            assert_eq!(error, PreprocessError { file: file.to_string(), line, message: message.to_string() }, "{source}");
+        }
+    }
+}
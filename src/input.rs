@@ -0,0 +1,360 @@
+use std::{collections::{HashMap, HashSet}, fs, path::{Path, PathBuf}, time::{Instant, SystemTime}};
+
+use glfw::{Action, Key, MouseButton};
+
+use crate::config::{parse_config, ConfigError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Sprint,
+    Jump,
+    BreakBlock,
+    ToggleFly,
+    ToggleMetrics,
+    ToggleHud,
+    ToggleMotionBlur,
+    ToggleFxaa,
+    ToggleBloom,
+    ToggleToneMap,
+    RecordPath,
+    PlayPath,
+    ReloadBindings,
+    Quit,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 20] = [
+        InputAction::MoveForward,
+        InputAction::MoveBackward,
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::MoveUp,
+        InputAction::MoveDown,
+        InputAction::Sprint,
+        InputAction::Jump,
+        InputAction::BreakBlock,
+        InputAction::ToggleFly,
+        InputAction::ToggleMetrics,
+        InputAction::ToggleHud,
+        InputAction::ToggleMotionBlur,
+        InputAction::ToggleFxaa,
+        InputAction::ToggleBloom,
+        InputAction::ToggleToneMap,
+        InputAction::RecordPath,
+        InputAction::PlayPath,
+        InputAction::ReloadBindings,
+        InputAction::Quit,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputAction::MoveForward => "move_forward",
+            InputAction::MoveBackward => "move_backward",
+            InputAction::MoveLeft => "move_left",
+            InputAction::MoveRight => "move_right",
+            InputAction::MoveUp => "move_up",
+            InputAction::MoveDown => "move_down",
+            InputAction::Sprint => "sprint",
+            InputAction::Jump => "jump",
+            InputAction::BreakBlock => "break_block",
+            InputAction::ToggleFly => "toggle_fly",
+            InputAction::ToggleMetrics => "toggle_metrics",
+            InputAction::ToggleHud => "toggle_hud",
+            InputAction::ToggleMotionBlur => "toggle_motion_blur",
+            InputAction::ToggleFxaa => "toggle_fxaa",
+            InputAction::ToggleBloom => "toggle_bloom",
+            InputAction::ToggleToneMap => "toggle_tone_map",
+            InputAction::RecordPath => "record_path",
+            InputAction::PlayPath => "play_path",
+            InputAction::ReloadBindings => "reload_bindings",
+            InputAction::Quit => "quit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    fn default_bindings(&self) -> Vec<Binding> {
+        use Binding::Key as K;
+
+        match self {
+            InputAction::MoveForward => vec![K(Key::W)],
+            InputAction::MoveBackward => vec![K(Key::S)],
+            InputAction::MoveLeft => vec![K(Key::A)],
+            InputAction::MoveRight => vec![K(Key::D)],
+            InputAction::MoveUp => vec![K(Key::Space)],
+            InputAction::MoveDown => vec![K(Key::LeftControl)],
+            InputAction::Sprint => vec![K(Key::LeftShift), K(Key::RightShift)],
+            InputAction::Jump => vec![K(Key::Space)],
+            InputAction::BreakBlock => vec![Binding::Mouse(MouseButton::Button1)],
+            InputAction::ToggleFly => vec![K(Key::F)],
+            InputAction::ToggleMetrics => vec![K(Key::F2)],
+            InputAction::ToggleHud => vec![K(Key::F3)],
+            InputAction::ToggleMotionBlur => vec![K(Key::F6)],
+            InputAction::ToggleFxaa => vec![K(Key::F7)],
+            InputAction::ToggleBloom => vec![K(Key::F8)],
+            InputAction::ToggleToneMap => vec![K(Key::F11)],
+            InputAction::RecordPath => vec![K(Key::F9)],
+            InputAction::PlayPath => vec![K(Key::F10)],
+            InputAction::ReloadBindings => vec![K(Key::F5)],
+            InputAction::Quit => vec![K(Key::Escape)],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    pub fn parse(name: &str) -> Option<Self> {
+        let mouse = match name.to_lowercase().as_str() {
+            "mouseleft" | "mouse1" => Some(MouseButton::Button1),
+            "mouseright" | "mouse2" => Some(MouseButton::Button2),
+            "mousemiddle" | "mouse3" => Some(MouseButton::Button3),
+            "mouse4" => Some(MouseButton::Button4),
+            "mouse5" => Some(MouseButton::Button5),
+            _ => None,
+        };
+        if let Some(button) = mouse {
+            return Some(Binding::Mouse(button));
+        }
+
+        ALL_KEYS.iter()
+            .find(|key| format!("{key:?}").eq_ignore_ascii_case(name))
+            .map(|key| Binding::Key(*key))
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{key:?}"),
+            Binding::Mouse(MouseButton::Button1) => "MouseLeft".to_string(),
+            Binding::Mouse(MouseButton::Button2) => "MouseRight".to_string(),
+            Binding::Mouse(MouseButton::Button3) => "MouseMiddle".to_string(),
+            Binding::Mouse(button) => format!("Mouse{}", *button as i32 + 1),
+        }
+    }
+
+    fn is_down(&self, window: &glfw::Window) -> bool {
+        match self {
+            Binding::Key(key) => window.get_key(*key) == Action::Press,
+            Binding::Mouse(button) => window.get_mouse_button(*button) == Action::Press,
+        }
+    }
+}
+
+// action -> bindings, backed by a config file that can be edited while the game runs
+pub struct InputMap {
+    bindings: HashMap<InputAction, Vec<Binding>>,
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self {
+            bindings: InputAction::ALL.iter().map(|a| (*a, a.default_bindings())).collect(),
+            path: None,
+            modified: None,
+            last_check: Instant::now(),
+        }
+    }
+
+    // loads the bindings from `path`, writing the defaults there on first run
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let mut map = Self::new();
+        map.path = Some(path.to_path_buf());
+
+        if !path.exists() {
+            if let Err(e) = fs::write(path, map.to_config_string()) {
+                println!("ERROR::INPUT:: could not write default bindings to {}: {e}", path.display());
+            }
+        }
+        map.reload();
+
+        map
+    }
+
+    pub fn reload(&mut self) {
+        let Some(path) = &self.path else { return };
+        self.modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        match fs::read_to_string(path) {
+            Ok(text) => match Self::parse(&text) {
+                Ok(bindings) => self.bindings = bindings,
+                Err(e) => println!("ERROR::INPUT:: {}: {e}, keeping the previous bindings", path.display()),
+            },
+            Err(e) => println!("ERROR::INPUT:: could not read {}: {e}", path.display()),
+        }
+    }
+
+    // cheap enough to call every frame, only touches the disk about once a second
+    pub fn reload_if_changed(&mut self) {
+        if self.last_check.elapsed().as_secs_f32() < 1.0 {
+            return;
+        }
+        self.last_check = Instant::now();
+
+        let Some(path) = &self.path else { return };
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified != self.modified {
+            self.reload();
+        }
+    }
+
+    // actions missing from the file keep their default bindings, `action =` unbinds it
+    pub fn parse(text: &str) -> Result<HashMap<InputAction, Vec<Binding>>, ConfigError> {
+        let mut bindings: HashMap<InputAction, Vec<Binding>> = InputAction::ALL.iter()
+            .map(|a| (*a, a.default_bindings()))
+            .collect();
+
+        for entry in parse_config(text)? {
+            let action = InputAction::from_name(&entry.key)
+                .ok_or_else(|| ConfigError::new(entry.line, format!("unknown action `{}`", entry.key)))?;
+
+            let mut keys = Vec::new();
+            for name in entry.value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                let binding = Binding::parse(name)
+                    .ok_or_else(|| ConfigError::new(entry.line, format!("unknown key `{name}`")))?;
+                keys.push(binding);
+            }
+            bindings.insert(action, keys);
+        }
+
+        Ok(bindings)
+    }
+
+    pub fn to_config_string(&self) -> String {
+        let mut out = String::from(
+            "# key bindings, `action = Key, OtherKey`\n\
+             # keys use glfw names (W, LeftShift, Space, F5, Num1...), mouse buttons are MouseLeft, MouseRight, MouseMiddle\n"
+        );
+
+        for action in InputAction::ALL {
+            let keys: Vec<String> = self.bindings(action).iter().map(Binding::name).collect();
+            out += &format!("{} = {}\n", action.name(), keys.join(", "));
+        }
+
+        out
+    }
+
+    pub fn bindings(&self, action: InputAction) -> &[Binding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+// which actions are down this frame, sampled once so the rest of the frame doesn't touch glfw
+#[derive(Clone, Default)]
+pub struct InputState {
+    held: HashSet<InputAction>,
+    previous: HashSet<InputAction>,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, map: &InputMap, window: &glfw::Window) {
+        self.previous = std::mem::take(&mut self.held);
+
+        for action in InputAction::ALL {
+            if map.bindings(action).iter().any(|b| b.is_down(window)) {
+                self.held.insert(action);
+            }
+        }
+    }
+
+    pub fn held(&self, action: InputAction) -> bool {
+        self.held.contains(&action)
+    }
+
+    // down this frame but not the last one
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.held.contains(&action) && !self.previous.contains(&action)
+    }
+}
+
+const ALL_KEYS: [Key; 120] = [
+    Key::Space, Key::Apostrophe, Key::Comma, Key::Minus, Key::Period, Key::Slash,
+    Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+    Key::Semicolon, Key::Equal,
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::LeftBracket, Key::Backslash, Key::RightBracket, Key::GraveAccent, Key::World1, Key::World2,
+    Key::Escape, Key::Enter, Key::Tab, Key::Backspace, Key::Insert, Key::Delete,
+    Key::Right, Key::Left, Key::Down, Key::Up, Key::PageUp, Key::PageDown, Key::Home, Key::End,
+    Key::CapsLock, Key::ScrollLock, Key::NumLock, Key::PrintScreen, Key::Pause,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+    Key::F13, Key::F14, Key::F15, Key::F16, Key::F17, Key::F18, Key::F19, Key::F20, Key::F21, Key::F22, Key::F23,
+    Key::F24, Key::F25,
+    Key::Kp0, Key::Kp1, Key::Kp2, Key::Kp3, Key::Kp4, Key::Kp5, Key::Kp6, Key::Kp7, Key::Kp8, Key::Kp9,
+    Key::KpDecimal, Key::KpDivide, Key::KpMultiply, Key::KpSubtract, Key::KpAdd, Key::KpEnter, Key::KpEqual,
+    Key::LeftShift, Key::LeftControl, Key::LeftAlt, Key::LeftSuper,
+    Key::RightShift, Key::RightControl, Key::RightAlt, Key::RightSuper, Key::Menu,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bindings() {
+        let cases: &[(&str, InputAction, Vec<Binding>)] = &[
+            ("", InputAction::Jump, vec![Binding::Key(Key::Space)]),
+            ("jump = J", InputAction::Jump, vec![Binding::Key(Key::J)]),
+            ("# comment\n\njump = j # lowercase", InputAction::Jump, vec![Binding::Key(Key::J)]),
+            ("sprint = LeftShift, MouseRight", InputAction::Sprint, vec![Binding::Key(Key::LeftShift), Binding::Mouse(MouseButton::Button2)]),
+            ("break_block = mouse4", InputAction::BreakBlock, vec![Binding::Mouse(MouseButton::Button4)]),
+            ("quit =", InputAction::Quit, vec![]),
+            ("jump = J\njump = K", InputAction::Jump, vec![Binding::Key(Key::K)]), // the last one wins
+            ("jump = J", InputAction::MoveForward, vec![Binding::Key(Key::W)]), // untouched keep their defaults
+        ];
+
+        for (text, action, expected) in cases {
+            let bindings = InputMap::parse(text).unwrap();
+            assert_eq!(&bindings[action], expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_bad_bindings() {
+        let cases = [
+            ("fly_faster = F", 1, "unknown action"),
+            ("jump = Space\nmove_forward = NotAKey", 2, "unknown key"),
+            ("jump Space", 1, "expected `key = value`"),
+        ];
+
+        for (text, line, message) in cases {
+            let error = InputMap::parse(text).unwrap_err();
+            assert_eq!(error.line, line, "{text:?}");
+            assert!(error.message.contains(message), "{text:?}: {}", error.message);
+        }
+    }
+
+    #[test]
+    fn defaults_round_trip() {
+        let map = InputMap::new();
+        let parsed = InputMap::parse(&map.to_config_string()).unwrap();
+        for action in InputAction::ALL {
+            assert_eq!(parsed[&action], map.bindings(action), "{}", action.name());
+        }
+    }
+}
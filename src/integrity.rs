@@ -0,0 +1,283 @@
+use std::collections::{HashMap, HashSet};
+
+use cgmath::Vector3;
+
+use crate::{block, world::{voxel_in_chunks, Chunk, Voxel, World}};
+
+pub const MAX_CLUSTER: usize = 4096; // anything bigger is taken to be held up, that's terrain
+const GRAVITY: f32 = 20.0; // blocks per second squared
+
+const NEIGHBOURS: [Vector3<i32>; 6] = [
+    Vector3::new(-1, 0, 0),
+    Vector3::new(1, 0, 0),
+    Vector3::new(0, -1, 0),
+    Vector3::new(0, 1, 0),
+    Vector3::new(0, 0, -1),
+    Vector3::new(0, 0, 1),
+];
+
+// what happens to blocks that lose their connection to the ground
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Detached {
+    Off, // they float, nothing is checked
+    Fall, // they drop as one body until they land
+    Report, // they stay, and a `DetachedCluster` is reported
+}
+
+impl Detached {
+    pub const ALL: [Detached; 3] = [Detached::Off, Detached::Fall, Detached::Report];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Detached::Off => "off",
+            Detached::Fall => "fall",
+            Detached::Report => "report",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|detached| detached.name() == name)
+    }
+}
+
+// solid blocks that nothing solid connects to the ground anymore
+#[derive(Clone, Debug)]
+pub struct DetachedCluster {
+    pub voxels: Vec<Vector3<i32>>,
+}
+
+// a detached cluster on its way down. its voxels stay in the world and move a whole block at a time
+struct FallingBody {
+    voxels: Vec<(Vector3<i32>, Voxel)>,
+    velocity: f32,
+    fallen: f32, // since the last whole block
+}
+
+// connectivity checks after blocks are removed. every solid block next to the hole is flood filled
+// through solid faces, a fill that runs into a chunk that isn't loaded or grows past `MAX_CLUSTER`
+// counts as grounded, one that runs out of blocks first has come loose
+pub struct Integrity {
+    pub mode: Detached,
+    pub events: Vec<DetachedCluster>, // with `Detached::Report`, for whoever wants them to drain
+    bodies: Vec<FallingBody>,
+    falling: HashSet<Vector3<i32>>, // every voxel of every body
+    moved: HashSet<Vector3<i32>>, // set by the bodies this tick, those changes don't need checking
+}
+
+impl Integrity {
+    pub fn new(mode: Detached) -> Self {
+        Self { mode, events: Vec::new(), bodies: Vec::new(), falling: HashSet::new(), moved: HashSet::new() }
+    }
+
+    pub fn falling_bodies(&self) -> usize {
+        self.bodies.len()
+    }
+
+    // whether `pos` is part of a body that's falling, those only move with their body
+    pub fn is_falling(&self, pos: Vector3<i32>) -> bool {
+        self.falling.contains(&pos)
+    }
+
+    // for a voxel that was solid and isn't anymore
+    pub fn check(&mut self, world: &World, removed: Vector3<i32>) {
+        if self.mode == Detached::Off || self.moved.contains(&removed) {
+            return;
+        }
+
+        let mut grounded = HashSet::new();
+        let mut detached = HashSet::new();
+        for offset in NEIGHBOURS {
+            let start = removed + offset;
+            if !is_solid(world, start) || self.falling.contains(&start) || grounded.contains(&start) || detached.contains(&start) {
+                continue;
+            }
+
+            match fill(&world.chunks, start, &self.falling, &grounded) {
+                Ok(cluster) => {
+                    detached.extend(cluster.iter().copied());
+                    self.detach(world, cluster);
+                },
+                Err(visited) => grounded.extend(visited),
+            }
+        }
+    }
+
+    fn detach(&mut self, world: &World, cluster: Vec<Vector3<i32>>) {
+        match self.mode {
+            Detached::Off => {},
+            Detached::Fall => {
+                let voxels = cluster.into_iter().filter_map(|pos| world.voxel_at(pos).map(|voxel| (pos, *voxel))).collect::<Vec<_>>();
+                self.falling.extend(voxels.iter().map(|(pos, _)| *pos));
+                self.bodies.push(FallingBody { voxels, velocity: 0.0, fallen: 0.0 });
+            },
+            Detached::Report => self.events.push(DetachedCluster { voxels: cluster }),
+        }
+    }
+
+    // moves the falling bodies, the ones that land become ordinary blocks again
+    pub fn step(&mut self, world: &mut World, dt: f32) {
+        self.moved.clear();
+
+        let mut bodies = std::mem::take(&mut self.bodies);
+        bodies.retain_mut(|body| {
+            body.velocity += GRAVITY * dt;
+            body.fallen += body.velocity * dt;
+            while body.fallen >= 1.0 {
+                body.fallen -= 1.0;
+                if !self.move_down(world, body) {
+                    for (pos, _) in &body.voxels {
+                        self.falling.remove(pos);
+                    }
+                    return false;
+                }
+            }
+            true
+        });
+        self.bodies = bodies;
+    }
+
+    // one block down, or false if anything outside the body is in the way
+    fn move_down(&mut self, world: &mut World, body: &mut FallingBody) -> bool {
+        let own: HashSet<_> = body.voxels.iter().map(|(pos, _)| *pos).collect();
+        let blocked = body.voxels.iter().any(|(pos, _)| {
+            let below = pos - Vector3::unit_y();
+            !own.contains(&below) && world.voxel_at(below).is_none_or(|voxel| block::block(voxel.id()).solid)
+        });
+        if blocked {
+            return false;
+        }
+
+        // cleared first so the body doesn't overwrite its own voxels, fluid in the way is displaced
+        for (pos, _) in &body.voxels {
+            world.set_voxel(*pos, Voxel::air());
+            self.moved.insert(*pos);
+            self.falling.remove(pos);
+        }
+        for (pos, voxel) in &mut body.voxels {
+            *pos -= Vector3::unit_y();
+            world.set_voxel(*pos, *voxel);
+            self.moved.insert(*pos);
+            self.falling.insert(*pos);
+        }
+
+        true
+    }
+}
+
+fn is_solid(world: &World, pos: Vector3<i32>) -> bool {
+    world.voxel_at(pos).is_some_and(|voxel| block::block(voxel.id()).solid)
+}
+
+// the solid blocks connected to `start`, or everything visited on the way if it's grounded.
+// bodies already falling don't connect anything
+fn fill(
+    chunks: &HashMap<Vector3<i32>, Chunk>,
+    start: Vector3<i32>,
+    falling: &HashSet<Vector3<i32>>,
+    grounded: &HashSet<Vector3<i32>>,
+) -> Result<Vec<Vector3<i32>>, HashSet<Vector3<i32>>> {
+    let mut visited = HashSet::from([start]);
+    let mut cluster = vec![start];
+    let mut i = 0;
+    while i < cluster.len() {
+        let pos = cluster[i];
+        i += 1;
+
+        for offset in NEIGHBOURS {
+            let next = pos + offset;
+            let Some(voxel) = voxel_in_chunks(chunks, next) else {
+                return Err(visited);
+            };
+            if !block::block(voxel.id()).solid || falling.contains(&next) || !visited.insert(next) {
+                continue;
+            }
+            if grounded.contains(&next) || cluster.len() >= MAX_CLUSTER {
+                return Err(visited);
+            }
+            cluster.push(next);
+        }
+    }
+
+    Ok(cluster)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one empty chunk at the origin, nothing around it is loaded
+    fn chunks(solid: impl IntoIterator<Item = (i32, i32, i32)>) -> HashMap<Vector3<i32>, Chunk> {
+        let mut chunk = Chunk::empty(Vector3::new(0.0, 0.0, 0.0));
+        for (x, y, z) in solid {
+            chunk.set_voxel(x as usize, y as usize, z as usize, Voxel::new(block::GROUND));
+        }
+
+        HashMap::from([(Vector3::new(0, 0, 0), chunk)])
+    }
+
+    fn cube(from: i32, to: i32) -> impl Iterator<Item = (i32, i32, i32)> {
+        (from..to).flat_map(move |x| (from..to).flat_map(move |y| (from..to).map(move |z| (x, y, z))))
+    }
+
+    fn pillar(base: i32) -> impl Iterator<Item = (i32, i32, i32)> {
+        (base..8).map(|y| (5, y, 5))
+    }
+
+    #[test]
+    fn cut_pillar_is_detached() {
+        // a floor touching the edge of the chunk holds the pillar up, until its bottom block goes
+        let floor = (0..24).flat_map(|x| (0..24).map(move |z| (x, 0, z)));
+        let chunks = chunks(floor.chain(pillar(2)));
+
+        let cluster = fill(&chunks, Vector3::new(5, 4, 5), &HashSet::new(), &HashSet::new()).unwrap();
+        let mut ys: Vec<_> = cluster.iter().map(|pos| pos.y).collect();
+        ys.sort();
+        assert_eq!(ys, (2..8).collect::<Vec<_>>());
+        assert!(cluster.iter().all(|pos| (pos.x, pos.z) == (5, 5)));
+
+        assert!(fill(&chunks, Vector3::new(5, 0, 5), &HashSet::new(), &HashSet::new()).is_err());
+    }
+
+    #[test]
+    fn falling_and_grounded_blocks() {
+        let chunks = chunks(pillar(1).chain([(5, 0, 0), (5, 0, 1), (5, 0, 2), (5, 0, 3), (5, 0, 4), (5, 0, 5)]));
+        let start = Vector3::new(5, 6, 5);
+        assert!(fill(&chunks, start, &HashSet::new(), &HashSet::new()).is_err());
+
+        // held up only through a body that's falling itself
+        let falling = HashSet::from([Vector3::new(5, 1, 5)]);
+        assert_eq!(fill(&chunks, start, &falling, &HashSet::new()).map(|cluster| cluster.len()), Ok(6));
+
+        // running into something an earlier fill found grounded stops early
+        let grounded = HashSet::from([Vector3::new(5, 3, 5)]);
+        let visited = fill(&chunks, start, &HashSet::new(), &grounded).unwrap_err();
+        assert!(visited.contains(&Vector3::new(5, 3, 5)));
+        assert!(!visited.contains(&Vector3::new(5, 2, 5)));
+    }
+
+    #[test]
+    fn touching_an_unloaded_chunk_is_grounded() {
+        for edge in [(0, 5, 5), (23, 5, 5), (5, 0, 5), (5, 23, 5), (5, 5, 0), (5, 5, 23)] {
+            let chunks = chunks([edge]);
+            let (x, y, z) = edge;
+            assert!(fill(&chunks, Vector3::new(x, y, z), &HashSet::new(), &HashSet::new()).is_err(), "{edge:?}");
+        }
+
+        let chunks = chunks([(1, 5, 5)]);
+        assert!(fill(&chunks, Vector3::new(1, 5, 5), &HashSet::new(), &HashSet::new()).is_ok());
+    }
+
+    #[test]
+    fn clusters_past_the_limit_are_grounded() {
+        let start = Vector3::new(10, 10, 10);
+
+        let small = chunks(cube(2, 12));
+        assert_eq!(fill(&small, start, &HashSet::new(), &HashSet::new()).map(|cluster| cluster.len()), Ok(1000));
+
+        // 20^3 blocks floating in the middle of the chunk, more than `MAX_CLUSTER`
+        let big = chunks(cube(2, 22));
+        let visited = fill(&big, start, &HashSet::new(), &HashSet::new()).unwrap_err();
+        assert!(visited.len() > MAX_CLUSTER);
+        assert!(visited.len() < 20 * 20 * 20);
+    }
+}
@@ -0,0 +1,35 @@
+// the unsafe fns are thin wrappers around gl calls, they're all safe to call as long as a gl context is
+// current on the calling thread and the objects they take are still alive, so it isn't repeated on each
+#![allow(clippy::missing_safety_doc)]
+
+pub mod shader;
+pub mod mesh;
+pub mod shaders;
+pub mod camera;
+pub mod util;
+pub mod world;
+pub mod lingering_framebuffer;
+pub mod terrain;
+pub mod player;
+pub mod config;
+pub mod input;
+pub mod settings;
+pub mod camera_path;
+pub mod metrics_overlay;
+pub mod text;
+pub mod debug_hud;
+pub mod crosshair;
+pub mod shader_watcher;
+pub mod glsl;
+pub mod post_process;
+pub mod sky;
+pub mod time_of_day;
+pub mod console;
+pub mod shadows;
+pub mod mesh_arena;
+pub mod block;
+pub mod block_textures;
+pub mod fluid;
+pub mod block_updates;
+pub mod integrity;
+pub mod biomes;
@@ -9,9 +9,9 @@ pub struct LingeringFramebuffer {
     pub fbo: u32,
     pub texture: u32,
     rbo: u32,
+    pub resolution: (i32, i32),
 }
 
-//todo: add motion blur
 impl LingeringFramebuffer {
     pub unsafe fn new(width: f32, height: f32, resolution_x: i32, resolution_y: i32, shader: &Shader) -> Self {
         let quad_vertices: [GLfloat; 24] = [
@@ -112,9 +112,16 @@ impl LingeringFramebuffer {
             fbo,
             rbo,
             texture,
+            resolution: (resolution_x, resolution_y),
         }
     }
 
+    // render into the texture instead of the screen, the caller restores the viewport afterwards
+    pub unsafe fn bind(&self) {
+        BindFramebuffer(FRAMEBUFFER, self.fbo);
+        Viewport(0, 0, self.resolution.0, self.resolution.1);
+    }
+
     pub unsafe fn draw(&self, shader: &Shader, width: f32, height: f32) {
         //shader.uniform_1i(cstr!("screenTex"), 0);
         Disable(DEPTH_TEST);
@@ -5,29 +5,47 @@ use cgmath::vec3;
 use glfw::*;
 use gl::*;
 use gl::types::*;
-use lingering_framebuffer::LingeringFramebuffer;
 use rand::random;
 use tokio::{spawn, sync::{watch, Mutex}};
-use util::{rand_betw, SecondOrderDynamics};
-use world::{Chunk, World};
 
-use crate::{camera::Camera, mesh::{Mesh, Vertex}, shader::Shader};
-
-mod shader;
-mod mesh;
-mod shaders;
-mod camera;
-mod util;
-mod world;
-mod lingering_framebuffer;
+use minecraftp::{
+    biomes::{BiomeGenerator, SEA_LEVEL},
+    block,
+    block_textures::{BlockTextures, BLOCK_TEXTURE_DIR},
+    block_updates::BlockUpdates,
+    camera::{Camera, CAMERA_BLOCK_SIZE},
+    camera_path::{CameraPath, PathPlayer, PathRecorder},
+    console::Console,
+    crosshair::Crosshair,
+    debug_hud::DebugHud,
+    glsl::{self, Defines},
+    input::{InputAction, InputMap, InputState},
+    metrics_overlay::{MetricsOverlay, Scale},
+    mesh::{Mesh, Vertex},
+    player::{Player, PlayerInput},
+    post_process::{PostPass, PostProcessor},
+    settings::Settings,
+    shadows::ShadowMaps,
+    shader::{ShaderError, UniformBuffer, CAMERA_BLOCK_BINDING},
+    shader_watcher::{self, ShaderWatcher},
+    shaders,
+    sky::Sky,
+    terrain::{EdgeMode, HeightmapGenerator},
+    text::TextRenderer,
+    time_of_day::WorldClock,
+    util::{rand_betw, SecondOrderDynamics},
+    world::{Voxel, World, REACH},
+};
 
 #[tokio::main]
 async fn main() {
+    let settings = Settings::load("settings.cfg");
+
     let mut glfw = glfw::init(fail_on_errors!()).unwrap();
     glfw.window_hint(glfw::WindowHint::ContextVersion(3, 3));
 
      // Create a windowed mode window and its OpenGL context
-    let (mut window, events) = glfw.create_window(1000, 1000, "g-fl", glfw::WindowMode::Windowed)
+    let (mut window, events) = glfw.create_window(settings.window_width, settings.window_height, "g-fl", glfw::WindowMode::Windowed)
          .expect("Failed to create GLFW window.");
 
     glfw.window_hint(
@@ -35,7 +53,7 @@ async fn main() {
     );
     
     window.make_current();
-    glfw.set_swap_interval(SwapInterval::Sync(0));
+    glfw.set_swap_interval(SwapInterval::Sync(settings.vsync as u32));
     window.set_key_polling(true);
     window.set_cursor_pos_polling(true);
     window.set_cursor_mode(CursorMode::Disabled);
@@ -61,13 +79,12 @@ async fn main() {
                 }).collect(),
     );
 
-    let mesh_shader_pipeline = Shader::new_pipeline(shaders::MESH_SHADER_VS, shaders::MESH_SHADER_FS);
-    let graph_shader_pipeline = Shader::new_pipeline(shaders::GRAPH_SHADER_VS, shaders::GRAPH_SHADER_FS);
-    let lingering_framebuffer_shader_pipeline = Shader::new_pipeline(shaders::LINGERING_SHADER_VS, shaders::LINGERING_SHADER_FS);
-    //unsafe { mesh_shader_pipeline.use_shader(); };
+    let camera_block = unsafe { UniformBuffer::new(CAMERA_BLOCK_BINDING, CAMERA_BLOCK_SIZE) };
+    let block_textures = unsafe { BlockTextures::load(BLOCK_TEXTURE_DIR) };
+    let _block_palette = unsafe { block::upload_palette(&block_textures) };
     
     let mut camera = Camera::new();
-    camera.pos_x = vec3(-1000.0, -1000.0, -1000.0); // the game works better here
+    camera.apply_settings(&settings);
     
     let mut sod = SecondOrderDynamics::new(2.5, 0.8, 0.5, vec3(0.0, 0.0, 0.0));
     let y = sod.update(0.0016, camera.pos_x);
@@ -76,20 +93,62 @@ async fn main() {
     // let chunk_mesh_data = chunk.gen_mesh_data_culled();
     // let chunk_mesh = Mesh::new(chunk_mesh_data.0, chunk_mesh_data.1);
 
-    let graph_mesh = Mesh::new(
-        vec![
-            Vertex::new(-1.0, -1.0, 0.0),
-        ],
-        vec![0]
-    );
-    
-    let fb_size = 0.5;
-    let fb_res = 250;
-    let lingering_framebuffer = unsafe {LingeringFramebuffer::new(fb_size, fb_size, fb_res, fb_res, &lingering_framebuffer_shader_pipeline)};
-    unsafe {lingering_framebuffer_shader_pipeline.uniform_1i(cstr!("screenTexture"), lingering_framebuffer.texture.try_into().unwrap());};
+    let mut metrics = or_exit(unsafe { MetricsOverlay::new(0.5, 250) });
+    // both in ms on the same scale, so their lines can be compared. the top is two frames at 60 fps
+    let frame_ms = Scale::Fixed(0.0, 2000.0 / 60.0);
+    metrics.add_series("frame time", [1.0, 0.3, 0.3], frame_ms);
+    metrics.add_series("chunks loaded", [0.3, 1.0, 0.4], Scale::Auto);
+    metrics.add_series("meshing time", [1.0, 0.9, 0.2], frame_ms);
+    metrics.add_series("draw calls", [0.3, 0.6, 1.0], Scale::Auto);
+    metrics.add_series("block updates", [0.2, 0.8, 0.9], Scale::Auto);
+    let mut text = or_exit(unsafe { TextRenderer::new() });
+    let mut hud = DebugHud::new();
+    let mut crosshair = or_exit(unsafe { Crosshair::new() });
+    let mut sky = or_exit(unsafe { Sky::new() });
+    sky.set_render_distance(settings.render_distance);
+    let mut shadows = settings.shadows.then(|| or_exit(unsafe { ShadowMaps::new(settings.shadow_resolution as i32) }));
+    let mut clock = WorldClock::new(settings.day_length, settings.start_hour);
+    let console = Console::spawn();
+    let mut block_updates = BlockUpdates::new(settings.detached_blocks);
+    let mut post = {
+        let (w, h) = window.get_framebuffer_size();
+        or_exit(unsafe { PostProcessor::new(w, h, &settings.post_passes) })
+    };
 
     let mut time = 0.0;
-    let mut world_buffer = World::new();
+    let mut world_buffer = match heightmap_from_args() {
+        Some(heightmap) => {
+            // start above the middle of the map instead of inside the perlin caves
+            let (w, d) = heightmap.size();
+            let scale = heightmap.horizontal_scale;
+            camera.pos_x = vec3(w as f32 * scale * 0.5, heightmap.max_height + 10.0, d as f32 * scale * 0.5);
+            or_exit(World::with_generator(Arc::new(heightmap)))
+        },
+        None => {
+            let terrain = BiomeGenerator::new(settings.seed);
+            camera.pos_x = vec3(0.5, terrain.height_at(0.0, 0.0).max(SEA_LEVEL) + 10.0, 0.5);
+            or_exit(World::with_generator(Arc::new(terrain)))
+        },
+    };
+    world_buffer.render_distance = settings.render_distance;
+    let mut mesh_defines = Defines::new();
+    if settings.fog {
+        mesh_defines.extend(glsl::flags(&["FOG"]));
+    }
+    if settings.shadows {
+        mesh_defines.extend(glsl::flags(&["SHADOWS"]));
+    }
+    let mut mesh_shader = or_exit(ShaderWatcher::new("mesh", shaders::MESH_SHADER_VS, shaders::MESH_SHADER_FS, mesh_defines));
+    unsafe { world_buffer.mesh_shader.delete(); }
+    world_buffer.mesh_shader = mesh_shader.shader.clone();
+    let mut player = Player::new(camera.pos_x);
+    player.walk_speed = settings.walk_speed;
+    let mut input_map = InputMap::load("input.cfg");
+    let mut input = InputState::new();
+
+    let mut path_recorder: Option<PathRecorder> = None;
+    let mut path_player = path_from_args().map(PathPlayer::new);
+    let exit_after_playback = path_player.is_some();
     
     while !window.should_close() {
         let now = std::time::Instant::now();
@@ -98,9 +157,8 @@ async fn main() {
         glfw.poll_events();
         for (_, event) in glfw::flush_messages(&events) {
             match event {
-                glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
-                    window.set_should_close(true)
-                },
+                // the path drives the camera during playback, the mouse resumes from wherever it is after
+                glfw::WindowEvent::CursorPos(_, _) if path_player.is_some() => camera.reset_mouse(),
                 glfw::WindowEvent::CursorPos(x, y) => {
                     camera.mouse_callback(x as f32, y as f32, &mut window);
                 }
@@ -108,59 +166,234 @@ async fn main() {
             }
         }
         
+        input_map.reload_if_changed();
+        if mesh_shader.reload_if_changed() {
+            world_buffer.mesh_shader = mesh_shader.shader.clone();
+        }
+        // the rest look their program up on every draw, so reloading is all they need
+        let shader_error = mesh_shader.error.clone().map(|error| (mesh_shader.name(), error)).or(shader_watcher::reload_all(
+            text.shaders().into_iter()
+                .chain(crosshair.shaders())
+                .chain(metrics.shaders())
+                .chain(sky.shaders())
+                .chain(post.shaders())
+                .chain(shadows.iter_mut().flat_map(ShadowMaps::shaders)),
+        ));
+        input.update(&input_map, &window);
+
+        if input.pressed(InputAction::Quit) {
+            window.set_should_close(true)
+        }
+        if input.pressed(InputAction::ReloadBindings) {
+            input_map.reload();
+        }
+        if input.pressed(InputAction::ToggleFly) {
+            camera.fly = !camera.fly;
+            player.set_eye_position(camera.pos_x);
+            player.unstick(&world_buffer);
+        }
+        if input.pressed(InputAction::ToggleMetrics) {
+            metrics.visible = !metrics.visible;
+        }
+        if input.pressed(InputAction::ToggleHud) {
+            hud.visible = !hud.visible;
+        }
+        for (action, pass) in POST_PASS_TOGGLES {
+            if input.pressed(action) {
+                let enabled = post.toggle(pass);
+                println!("{} {}", pass.name(), if enabled { "on" } else { "off" });
+            }
+        }
+        if input.pressed(InputAction::RecordPath) {
+            match path_recorder.take() {
+                Some(recorder) => match recorder.finish().save(CAMERA_PATH_FILE) {
+                    Ok(()) => println!("camera path saved to {CAMERA_PATH_FILE}"),
+                    Err(e) => println!("ERROR::CAMERA_PATH:: could not save {CAMERA_PATH_FILE}: {e}"),
+                },
+                None => path_recorder = Some(PathRecorder::new()),
+            }
+        }
+        if input.pressed(InputAction::PlayPath) {
+            path_player = match path_player.take() {
+                Some(_) => None,
+                None => match CameraPath::load(CAMERA_PATH_FILE) {
+                    Ok(path) => Some(PathPlayer::new(path)),
+                    Err(e) => {
+                        println!("ERROR::CAMERA_PATH:: could not load {CAMERA_PATH_FILE}: {e}");
+                        None
+                    },
+                },
+            };
+        }
+
+        while let Some(words) = console.poll() {
+            let args: Vec<&str> = words.iter().map(String::as_str).collect();
+            match args.as_slice() {
+                ["time", rest @ ..] => match clock.command(rest) {
+                    Ok(message) => println!("{message}"),
+                    Err(e) => println!("ERROR::CONSOLE:: {e}"),
+                },
+                ["place", name] => match block::by_name(name) {
+                    Some(id) => if !world_buffer.place_voxel_raycasting(camera.pos_x, camera.front, Voxel::new(id)) {
+                        println!("ERROR::CONSOLE:: nothing to place {name} against");
+                    },
+                    None => println!("ERROR::CONSOLE:: no block called `{name}`"),
+                },
+                [command, ..] => println!("ERROR::CONSOLE:: unknown command `{command}`, try `time` or `place`"),
+                [] => {},
+            }
+        }
+
         world_buffer.update().await;
 
-        if window.get_mouse_button(MouseButtonLeft) == Action::Press {
+        if input.held(InputAction::BreakBlock) && path_player.is_none() {
             world_buffer.remove_voxel_raycasting(camera.pos_x, camera.front);
         }
 
         world_buffer.camera_pos = camera.pos_x;
+        block_updates.update(&mut world_buffer, camera.dt);
+        for cluster in block_updates.integrity.events.drain(..) {
+            println!("detached {} blocks around {:?}", cluster.voxels.len(), cluster.voxels[0]);
+        }
 
         unsafe {
-            BindFramebuffer(FRAMEBUFFER, 0);
+            let (w, h) = window.get_framebuffer_size();
+            clock.update(camera.dt);
+            sky.apply(&clock.lighting());
+            if let Some(shadows) = &mut shadows {
+                shadows.render(&camera, sky.light_direction, sky.fog_end, &world_buffer);
+            }
+            post.begin(w, h);
             Enable(DEPTH_TEST); 
-            ClearColor(0.1, 0.2, 0.3, 1.0);
+            let [r, g, b] = settings.clear_color;
+            ClearColor(r, g, b, 1.0);
             Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
             
-            camera.send_uniforms(&mesh_shader_pipeline);
-            // my_mesh.draw(&mesh_shader_pipeline);
+            camera.send_uniforms(&camera_block);
+            sky.upload();
+            sky.draw();
 
             world_buffer.draw(&camera);
+            world_buffer.draw_translucent(&camera);
+            if let Some(hit) = world_buffer.raycast(camera.pos_x, camera.front, REACH) {
+                crosshair.draw_outline(&hit);
+            }
+            post.finish();
 
-            /*
-            // todo: add the graph in its own class
-            BindFramebuffer(FRAMEBUFFER, lingering_framebuffer.fbo);
-            Disable(DEPTH_TEST); 
+            crosshair.draw(w, h);
+            metrics.draw(w, h, &mut text);
+            hud.draw(&camera, &world_buffer, &clock, &mut text);
+            if let Some((name, error)) = &shader_error {
+                let message = format!("{name} shader error, keeping the last good program\n{error}");
+                text.queue_shadowed(&message, 8.0, h as f32 * 0.5, 1.0, [1.0, 0.3, 0.3, 1.0]);
+            }
+            text.flush(w, h);
+        }
 
-            graph_mesh.draw_points(&graph_shader_pipeline);
+        camera.update(sod.update(now.elapsed().as_secs_f32(), camera.pos_x));  
+        camera.frame(&window, &glfw);
+        if path_player.is_none() {
+            camera.input(&input);
+        }
 
-            plot_data(
-               &graph_shader_pipeline, 
-               (time / 10.0) % 1.0, 
-               (f32::sin(time) + f32::sin(time * 2.0) + f32::sin(time * 4.0)) / 4.0, 
-               fb_size,
-               fb_res as f32 / 1000.0,
-            );
+        metrics.push("frame time", camera.dt * 1000.0);
+        metrics.push("chunks loaded", world_buffer.chunks.len() as f32);
+        metrics.push("meshing time", world_buffer.stats.meshing_time * 1000.0);
+        metrics.push("draw calls", world_buffer.stats.draw_calls as f32);
+        metrics.push("block updates", block_updates.updated as f32);
 
-            BindFramebuffer(FRAMEBUFFER, 0);
-            lingering_framebuffer.draw(&lingering_framebuffer_shader_pipeline, 1.0, 1.0);
-            */
-            // mesh_shader_pipeline.use_shader();   
+        if !camera.fly && path_player.is_none() {
+            let player_input = PlayerInput {
+                wish_dir: camera.walk_direction(&input),
+                jump: input.held(InputAction::Jump),
+                sprint: input.held(InputAction::Sprint),
+            };
+            player.update(camera.dt, player_input, &world_buffer);
+            camera.pos_x = player.eye_position();
         }
 
-        camera.update(sod.update(now.elapsed().as_secs_f32(), camera.pos_x));  
-        camera.input(&mut window, &glfw);
+        if let Some(recorder) = &mut path_recorder {
+            recorder.record(&camera);
+        }
+        if let Some(playback) = &mut path_player {
+            let playing = playback.apply(&mut camera);
+            playback.record_frame(now.elapsed().as_secs_f32(), world_buffer.chunks.len(), world_buffer.chunks_generated);
+
+            if !playing {
+                println!("camera path playback: {}", playback.summary());
+                if let Err(e) = playback.save_frames(PLAYBACK_FRAMES_FILE) {
+                    println!("ERROR::CAMERA_PATH:: could not save {PLAYBACK_FRAMES_FILE}: {e}");
+                }
+                path_player = None;
+                if exit_after_playback {
+                    window.set_should_close(true);
+                }
+            }
+        }
 
         time+=now.elapsed().as_secs_f32();
      }
  }
 
-unsafe fn plot_data(shader: &Shader, x: f32, y: f32, size: f32, reso_ratio: f32) {
-    let one = reso_ratio * (1.0 / size);
+// the embedded shaders are expected to always build, if one doesn't there's nothing to draw with
+fn or_exit<T>(result: Result<T, ShaderError>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(e) => {
+            println!("ERROR::SHADER:: {e}");
+            std::process::exit(1);
+        },
+    }
+}
 
-    //let x_ranged = (x + 1.0) * 0.5;
-    let y_ranged = (y + 1.0) * 0.5;
+const POST_PASS_TOGGLES: [(InputAction, PostPass); 4] = [
+    (InputAction::ToggleMotionBlur, PostPass::MotionBlur),
+    (InputAction::ToggleFxaa, PostPass::Fxaa),
+    (InputAction::ToggleBloom, PostPass::Bloom),
+    (InputAction::ToggleToneMap, PostPass::ToneMap),
+];
 
-    shader.uniform_1f(cstr!("ofsx"), x * one);
-    shader.uniform_1f(cstr!("ofsy"), y_ranged * one);
+const CAMERA_PATH_FILE: &str = "camera_path.txt";
+const PLAYBACK_FRAMES_FILE: &str = "camera_path_frames.csv";
+
+// usage: minecraftp --play-path flythrough.txt, plays the path once and exits
+fn path_from_args() -> Option<CameraPath> {
+    let args: Vec<String> = std::env::args().collect();
+    let path = args.iter().position(|a| a == "--play-path").and_then(|i| args.get(i + 1))?;
+
+    match CameraPath::load(path) {
+        Ok(path) => Some(path),
+        Err(e) => {
+            println!("ERROR::CAMERA_PATH:: could not load {path}: {e}");
+            None
+        },
+    }
+}
+
+// usage: minecraftp --heightmap map.png [--height-range 0:64] [--scale 1] [--clamp]
+fn heightmap_from_args() -> Option<HeightmapGenerator> {
+    let args: Vec<String> = std::env::args().collect();
+    let value_of = |flag: &str| args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1));
+
+    let path = value_of("--heightmap")?;
+    let (min_height, max_height) = value_of("--height-range")
+        .and_then(|range| range.split_once(':'))
+        .and_then(|(min, max)| Some((min.parse().ok()?, max.parse().ok()?)))
+        .unwrap_or((0.0, 64.0));
+    let edge_mode = if args.iter().any(|a| a == "--clamp") { EdgeMode::Clamp } else { EdgeMode::Tile };
+
+    match HeightmapGenerator::from_file(path, min_height, max_height, edge_mode) {
+        Ok(mut heightmap) => {
+            if let Some(scale) = value_of("--scale").and_then(|s| s.parse().ok()) {
+                heightmap.horizontal_scale = scale;
+            }
+            Some(heightmap)
+        },
+        Err(e) => {
+            println!("ERROR::HEIGHTMAP:: could not load {path}: {e}");
+            None
+        },
+    }
 }
@@ -10,6 +10,7 @@ use std::mem::*;
 use std::ffi::*;
 use std::ptr;
 
+use crate::biomes::MAX_BIOMES;
 use crate::shader::Shader;
 use crate::util::rand_betw;
 
@@ -35,8 +36,66 @@ impl Vertex {
             color: (vec3(x, y, z) / 32.0) * perlin as f32,
         }
     }
+
+    // the layout of the vertex buffer bound to ARRAY_BUFFER, into the bound VAO
+    pub unsafe fn set_attributes() {
+        let size = size_of::<Vertex>() as i32;
+
+        EnableVertexAttribArray(0);
+        VertexAttribPointer(0, 3, FLOAT, FALSE, size, offset_of!(Vertex, position) as *const c_void);
+        EnableVertexAttribArray(1);
+        VertexAttribPointer(1, 3, FLOAT, FALSE, size, offset_of!(Vertex, color) as *const c_void);
+    }
+}
+
+
+pub const MAX_LIGHT: u32 = 15;
+
+// a chunk mesh vertex in 8 bytes, unpacked by voxel.glsl. `data` holds the position inside the
+// chunk (5 bits per axis, 0..=CHUNK_SIZE), the face index (3), ambient occlusion (2, 0 is fully
+// occluded), light (4) and how far the vertex is lowered in 16ths of a block (4, for fluid
+// surfaces). `block` holds the block id (8) the color and texture come from and the
+// texture coordinates (5 each), in blocks so the texture repeats across a quad covering several,
+// and the biome (4) whose tint it takes
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct VoxelVertex {
+    pub data: u32,
+    pub block: u32,
 }
 
+impl VoxelVertex {
+    pub fn new(pos: [u32; 3], face: u32, ao: u32, light: u32, block: u8, uv: [u32; 2]) -> Self {
+        let [x, y, z] = pos;
+        let [u, v] = uv;
+        debug_assert!(x < 32 && y < 32 && z < 32 && face < 6 && ao < 4 && light <= MAX_LIGHT);
+        debug_assert!(u < 32 && v < 32);
+
+        Self {
+            data: x | y << 5 | z << 10 | face << 15 | ao << 18 | light << 20,
+            block: block as u32 | u << 8 | v << 13,
+        }
+    }
+
+    // moves the vertex down by `drop` 16ths of a block, for fluids below full height
+    pub fn lowered(mut self, drop: u32) -> Self {
+        debug_assert!(drop < 16);
+        self.data |= drop << 24;
+        self
+    }
+
+    pub fn in_biome(mut self, biome: u8) -> Self {
+        debug_assert!((biome as usize) < MAX_BIOMES);
+        self.block |= (biome as u32) << 18;
+        self
+    }
+
+    // both words as one integer attribute, into the bound VAO
+    pub unsafe fn set_attributes() {
+        EnableVertexAttribArray(0);
+        VertexAttribIPointer(0, 2, UNSIGNED_INT, size_of::<VoxelVertex>() as i32, ptr::null());
+    }
+}
 
 #[derive(Clone)]
 pub struct Mesh {
@@ -78,12 +137,7 @@ impl Mesh {
         let data = &self.inds[0] as *const u32 as *const c_void;
         BufferData(ELEMENT_ARRAY_BUFFER, size, data, STATIC_DRAW);
 
-        let size = size_of::<Vertex>() as i32;
-
-        EnableVertexAttribArray(0);
-        VertexAttribPointer(0, 3, FLOAT, FALSE, size, offset_of!(Vertex, position) as *const c_void);
-        EnableVertexAttribArray(1);
-        VertexAttribPointer(1, 3, FLOAT, FALSE, size, offset_of!(Vertex, color) as *const c_void);
+        Vertex::set_attributes();
 
         BindVertexArray(0);
     }
@@ -95,6 +149,13 @@ impl Mesh {
         BindVertexArray(0);
     }
 
+    // for drawing many meshes with one shader, the caller has already called `use_shader`
+    pub unsafe fn draw_bound(&self) {
+        BindVertexArray(self.VAO);
+        DrawElements(TRIANGLES, self.inds.len() as i32, UNSIGNED_INT, ptr::null());
+        BindVertexArray(0);
+    }
+
     pub unsafe fn draw_points(&self, shader: &Shader) {
         BindVertexArray(self.VAO);
         shader.use_shader();
@@ -127,3 +188,38 @@ impl Mesh {
         BufferSubData(ELEMENT_ARRAY_BUFFER, 0, size, data);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the fields as shaders/voxel.glsl reads them back: pos, face, ao, light, drop, block, uv, biome
+    fn unpack(vertex: VoxelVertex) -> ([u32; 3], u32, u32, u32, u32, u32, [u32; 2], u32) {
+        let (data, block) = (vertex.data, vertex.block);
+        (
+            [data & 31, (data >> 5) & 31, (data >> 10) & 31],
+            (data >> 15) & 7,
+            (data >> 18) & 3,
+            (data >> 20) & 15,
+            (data >> 24) & 15,
+            block & 255,
+            [(block >> 8) & 31, (block >> 13) & 31],
+            (block >> 18) & 15,
+        )
+    }
+
+    #[test]
+    fn packs_what_the_shader_unpacks() {
+        let vertex = VoxelVertex::new([24, 24, 24], 5, 3, MAX_LIGHT, 255, [24, 24]).lowered(15).in_biome(15);
+        assert_eq!(unpack(vertex), ([24, 24, 24], 5, 3, 15, 15, 255, [24, 24], 15));
+
+        // nothing bleeds into the fields next to it
+        let vertex = VoxelVertex::new([0, 24, 0], 0, 3, 0, 0, [24, 0]).lowered(0).in_biome(15);
+        assert_eq!(unpack(vertex), ([0, 24, 0], 0, 3, 0, 0, 0, [24, 0], 15));
+        let vertex = VoxelVertex::new([24, 0, 24], 5, 0, MAX_LIGHT, 255, [0, 24]).lowered(15).in_biome(0);
+        assert_eq!(unpack(vertex), ([24, 0, 24], 5, 0, 15, 15, 255, [0, 24], 0));
+
+        let vertex = VoxelVertex::new([3, 17, 9], 2, 1, 7, 42, [5, 11]).lowered(9).in_biome(6);
+        assert_eq!(unpack(vertex), ([3, 17, 9], 2, 1, 7, 9, 42, [5, 11], 6));
+    }
+}
@@ -0,0 +1,379 @@
+use std::{collections::HashMap, ffi::c_void, mem::size_of, ops::Range, ptr};
+
+use cgmath::Vector3;
+use gl::*;
+use gl::types::*;
+
+use crate::{mesh::VoxelVertex, shader::{Shader, CHUNK_ORIGINS_UNIT}, world::CHUNK_SIZE};
+
+// vertices are handed out in pages, and every page belongs to exactly one chunk. the vertex shader
+// finds its chunk's origin from `gl_VertexID / pageVertices` (which includes the base vertex), so
+// the whole arena can go out in one multi-draw without a uniform per chunk
+pub const PAGE_VERTICES: usize = 512;
+const INITIAL_PAGES: usize = 256;
+const INITIAL_INDICES: usize = 1 << 20;
+
+// first fit over a sorted free list, neighbours are merged back together when freed
+#[derive(Clone, Debug, Default)]
+struct RangeAllocator {
+    capacity: usize,
+    free: Vec<Range<usize>>,
+}
+
+impl RangeAllocator {
+    fn alloc(&mut self, len: usize) -> Option<usize> {
+        let i = self.free.iter().position(|range| range.len() >= len)?;
+        let start = self.free[i].start;
+        self.free[i].start += len;
+        if self.free[i].is_empty() {
+            self.free.remove(i);
+        }
+
+        Some(start)
+    }
+
+    fn free(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let mut i = self.free.partition_point(|r| r.start < range.start);
+        self.free.insert(i, range);
+        if i + 1 < self.free.len() && self.free[i].end == self.free[i + 1].start {
+            self.free[i].end = self.free.remove(i + 1).end;
+        }
+        if i > 0 && self.free[i - 1].end == self.free[i].start {
+            self.free[i - 1].end = self.free.remove(i).end;
+            i -= 1;
+        }
+        debug_assert!(self.free[i].end <= self.capacity);
+    }
+
+    fn grow(&mut self, capacity: usize) {
+        let old = self.capacity;
+        self.capacity = capacity;
+        self.free(old..capacity);
+    }
+
+    fn used(&self) -> usize {
+        self.capacity - self.free.iter().map(Range::len).sum::<usize>()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Allocation {
+    first_page: usize,
+    pages: usize,
+    first_index: usize,
+    index_count: usize,
+}
+
+impl Allocation {
+    fn vertices(&self) -> Range<usize> {
+        self.first_page * PAGE_VERTICES..(self.first_page + self.pages) * PAGE_VERTICES
+    }
+}
+
+// every chunk mesh in a few large buffers. the buffers are only created on the first insert,
+// so a headless world can own one without a gl context. not `Clone`, a copy would share the
+// buffer names and delete them out from under the original when it grows
+#[derive(Default)]
+pub struct MeshArena {
+    allocations: HashMap<Vector3<i32>, Allocation>,
+    pages: RangeAllocator,
+    indices: RangeAllocator,
+    page_origins: Vec<[f32; 4]>, // copy of what's in `origin_buffer`, to re-upload on growth
+
+    vao: u32,
+    vbo: u32,
+    ebo: u32,
+    origin_buffer: u32,
+    origin_texture: u32, // texture buffer view of `origin_buffer`
+}
+
+impl MeshArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, pos: &Vector3<i32>) -> bool {
+        self.allocations.contains_key(pos)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = &Vector3<i32>> {
+        self.allocations.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.allocations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allocations.is_empty()
+    }
+
+    // bytes in use and allocated on the gpu, for the metrics
+    pub fn memory(&self) -> (usize, usize) {
+        let used = self.pages.used() * PAGE_VERTICES * size_of::<VoxelVertex>() + self.indices.used() * size_of::<u32>();
+        let capacity = self.pages.capacity * PAGE_VERTICES * size_of::<VoxelVertex>() + self.indices.capacity * size_of::<u32>();
+        (used, capacity)
+    }
+
+    // replaces whatever mesh the chunk had. `inds` are relative to the start of `verts`
+    pub unsafe fn insert(&mut self, pos: Vector3<i32>, verts: &[VoxelVertex], inds: &[u32]) {
+        self.remove(&pos);
+        if verts.is_empty() || inds.is_empty() {
+            return;
+        }
+
+        let pages = verts.len().div_ceil(PAGE_VERTICES);
+        let first_page = match self.pages.alloc(pages) {
+            Some(page) => page,
+            None => {
+                self.grow_vertices(pages);
+                self.pages.alloc(pages).unwrap()
+            },
+        };
+        let first_index = match self.indices.alloc(inds.len()) {
+            Some(index) => index,
+            None => {
+                self.grow_indices(inds.len());
+                self.indices.alloc(inds.len()).unwrap()
+            },
+        };
+
+        let allocation = Allocation { first_page, pages, first_index, index_count: inds.len() };
+        self.allocations.insert(pos, allocation);
+
+        let origin = (pos * CHUNK_SIZE as i32).cast::<f32>().unwrap();
+        for page in &mut self.page_origins[first_page..first_page + pages] {
+            *page = [origin.x, origin.y, origin.z, 0.0];
+        }
+
+        upload(self.vbo, allocation.vertices().start * size_of::<VoxelVertex>(), verts);
+        upload(self.ebo, first_index * size_of::<u32>(), inds);
+        upload(self.origin_buffer, first_page * size_of::<[f32; 4]>(), &self.page_origins[first_page..first_page + pages]);
+    }
+
+    // no gl calls, the space is just handed back
+    pub fn remove(&mut self, pos: &Vector3<i32>) {
+        if let Some(allocation) = self.allocations.remove(pos) {
+            self.pages.free(allocation.first_page..allocation.first_page + allocation.pages);
+            self.indices.free(allocation.first_index..allocation.first_index + allocation.index_count);
+        }
+    }
+
+    // every chunk in `chunks` that has a mesh, in a single call with `shader` already in use.
+    // returns how many chunks were drawn
+    pub unsafe fn draw(&self, shader: &Shader, chunks: impl IntoIterator<Item = Vector3<i32>>) -> usize {
+        let mut counts: Vec<GLsizei> = Vec::new();
+        let mut offsets: Vec<*const c_void> = Vec::new();
+        let mut base_vertices: Vec<GLint> = Vec::new();
+
+        for pos in chunks {
+            let Some(allocation) = self.allocations.get(&pos) else {
+                continue;
+            };
+            counts.push(allocation.index_count as GLsizei);
+            offsets.push((allocation.first_index * size_of::<u32>()) as *const c_void);
+            base_vertices.push(allocation.vertices().start as GLint);
+        }
+        if counts.is_empty() {
+            return 0;
+        }
+
+        shader.set_uniform("pageVertices", PAGE_VERTICES as i32);
+        ActiveTexture(TEXTURE0 + CHUNK_ORIGINS_UNIT);
+        BindTexture(TEXTURE_BUFFER, self.origin_texture);
+        ActiveTexture(TEXTURE0);
+
+        BindVertexArray(self.vao);
+        MultiDrawElementsBaseVertex(
+            TRIANGLES,
+            counts.as_ptr(),
+            UNSIGNED_INT,
+            offsets.as_ptr(),
+            counts.len() as GLsizei,
+            base_vertices.as_mut_ptr(),
+        );
+        BindVertexArray(0);
+
+        counts.len()
+    }
+
+    pub unsafe fn delete(&mut self) {
+        if self.vao != 0 {
+            DeleteVertexArrays(1, &self.vao);
+            DeleteBuffers(1, &self.vbo);
+            DeleteBuffers(1, &self.ebo);
+            DeleteBuffers(1, &self.origin_buffer);
+            DeleteTextures(1, &self.origin_texture);
+        }
+        *self = Self::default();
+    }
+
+    // at least doubles, so a stream of new chunks doesn't copy the buffers every time
+    unsafe fn grow_vertices(&mut self, needed: usize) {
+        let old = self.pages.capacity;
+        let capacity = (old * 2).max(old + needed).max(INITIAL_PAGES);
+        self.pages.grow(capacity);
+        self.page_origins.resize(capacity, [0.0; 4]);
+
+        let vertex_size = PAGE_VERTICES * size_of::<VoxelVertex>();
+        self.vbo = grow_buffer(self.vbo, old * vertex_size, capacity * vertex_size);
+        self.origin_buffer = grow_buffer(self.origin_buffer, 0, capacity * size_of::<[f32; 4]>());
+        upload(self.origin_buffer, 0, &self.page_origins);
+
+        if self.origin_texture == 0 {
+            GenTextures(1, &mut self.origin_texture);
+        }
+        BindTexture(TEXTURE_BUFFER, self.origin_texture);
+        TexBuffer(TEXTURE_BUFFER, RGBA32F, self.origin_buffer);
+        BindTexture(TEXTURE_BUFFER, 0);
+
+        self.bind_vao();
+    }
+
+    unsafe fn grow_indices(&mut self, needed: usize) {
+        let old = self.indices.capacity;
+        let capacity = (old * 2).max(old + needed).max(INITIAL_INDICES);
+        self.indices.grow(capacity);
+
+        self.ebo = grow_buffer(self.ebo, old * size_of::<u32>(), capacity * size_of::<u32>());
+        self.bind_vao();
+    }
+
+    // the vao has to be pointed at the new buffers whenever they're replaced
+    unsafe fn bind_vao(&mut self) {
+        if self.vao == 0 {
+            GenVertexArrays(1, &mut self.vao);
+        }
+
+        BindVertexArray(self.vao);
+        BindBuffer(ARRAY_BUFFER, self.vbo);
+        if self.vbo != 0 {
+            VoxelVertex::set_attributes();
+        }
+        BindBuffer(ELEMENT_ARRAY_BUFFER, self.ebo);
+        BindVertexArray(0);
+        BindBuffer(ARRAY_BUFFER, 0);
+    }
+}
+
+// a bigger buffer with the first `keep` bytes of `old` copied over, `old` is deleted
+unsafe fn grow_buffer(old: u32, keep: usize, size: usize) -> u32 {
+    let mut buffer = 0;
+    GenBuffers(1, &mut buffer);
+    BindBuffer(COPY_WRITE_BUFFER, buffer);
+    BufferData(COPY_WRITE_BUFFER, size as GLsizeiptr, ptr::null(), DYNAMIC_DRAW);
+
+    if old != 0 {
+        BindBuffer(COPY_READ_BUFFER, old);
+        CopyBufferSubData(COPY_READ_BUFFER, COPY_WRITE_BUFFER, 0, 0, keep as GLsizeiptr);
+        BindBuffer(COPY_READ_BUFFER, 0);
+        DeleteBuffers(1, &old);
+    }
+    BindBuffer(COPY_WRITE_BUFFER, 0);
+
+    buffer
+}
+
+// through the copy target, binding ELEMENT_ARRAY_BUFFER would change whichever vao is bound
+unsafe fn upload<T: Copy>(buffer: u32, offset: usize, data: &[T]) {
+    BindBuffer(COPY_WRITE_BUFFER, buffer);
+    BufferSubData(
+        COPY_WRITE_BUFFER,
+        offset as GLintptr,
+        size_of_val(data) as GLsizeiptr,
+        data.as_ptr() as *const c_void,
+    );
+    BindBuffer(COPY_WRITE_BUFFER, 0);
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)] // the free list, one range is what it should be
+mod tests {
+    use super::*;
+
+    fn allocator(capacity: usize) -> RangeAllocator {
+        let mut allocator = RangeAllocator::default();
+        allocator.grow(capacity);
+        allocator
+    }
+
+    #[test]
+    fn freed_neighbours_merge() {
+        let mut ranges = allocator(30);
+        let [a, b, c] = [ranges.alloc(10), ranges.alloc(10), ranges.alloc(10)].map(Option::unwrap);
+        assert_eq!((a, b, c), (0, 10, 20));
+        assert!(ranges.free.is_empty());
+
+        ranges.free(b..b + 10);
+        assert_eq!(ranges.free, [10..20]);
+        ranges.free(c..c + 10); // merges with the one before
+        assert_eq!(ranges.free, [10..30]);
+        ranges.free(a..a + 10); // and with the one after
+        assert_eq!(ranges.free, [0..30]);
+
+        // the other order, both sides at once
+        let mut ranges = allocator(30);
+        let [a, b, c] = [ranges.alloc(10), ranges.alloc(10), ranges.alloc(10)].map(Option::unwrap);
+        ranges.free(a..a + 10);
+        ranges.free(c..c + 10);
+        assert_eq!(ranges.free, [0..10, 20..30]);
+        ranges.free(b..b + 10);
+        assert_eq!(ranges.free, [0..30]);
+    }
+
+    #[test]
+    fn fragmented_until_grown() {
+        let mut ranges = allocator(40);
+        let starts: Vec<_> = (0..4).map(|_| ranges.alloc(10).unwrap()).collect();
+        ranges.free(starts[0]..starts[0] + 10);
+        ranges.free(starts[2]..starts[2] + 10);
+
+        // 20 free, but not in one piece
+        assert_eq!(ranges.used(), 20);
+        assert_eq!(ranges.alloc(15), None);
+
+        ranges.grow(60);
+        assert_eq!(ranges.free, [0..10, 20..30, 40..60]);
+        assert_eq!(ranges.alloc(15), Some(40));
+        // first fit, the hole at the start comes before the space at the end
+        assert_eq!(ranges.alloc(5), Some(0));
+    }
+
+    #[test]
+    fn free_space_at_the_end_merges_into_growth() {
+        let mut ranges = allocator(20);
+        ranges.alloc(10).unwrap();
+        ranges.grow(40);
+        assert_eq!(ranges.free, [10..40]);
+        assert_eq!(ranges.alloc(30), Some(10));
+    }
+
+    #[test]
+    fn used_after_mixed_operations() {
+        let mut ranges = allocator(100);
+        assert_eq!(ranges.used(), 0);
+
+        let a = ranges.alloc(30).unwrap();
+        let b = ranges.alloc(20).unwrap();
+        assert_eq!(ranges.used(), 50);
+
+        ranges.free(a..a + 30);
+        assert_eq!(ranges.used(), 20);
+        ranges.free(5..5); // empty, ignored
+        assert_eq!(ranges.used(), 20);
+
+        assert_eq!(ranges.alloc(25), Some(0));
+        ranges.grow(150);
+        assert_eq!(ranges.used(), 45);
+        assert_eq!(ranges.capacity, 150);
+
+        ranges.free(b..b + 20);
+        ranges.free(0..25);
+        assert_eq!(ranges.used(), 0);
+        assert_eq!(ranges.free, [0..150]);
+    }
+}
@@ -0,0 +1,203 @@
+use std::{collections::VecDeque, ffi::c_void, mem::{size_of, size_of_val}};
+
+use gl::*;
+use gl::types::*;
+
+use crate::{glsl::Defines, lingering_framebuffer::LingeringFramebuffer, shader::ShaderError, shader_watcher::ShaderWatcher, shaders::*, text::TextRenderer};
+
+const HISTORY: usize = 240; // samples kept per series, one per frame
+const PADDING: f32 = 0.05; // in the framebuffer's ndc
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scale {
+    Fixed(f32, f32),
+    Auto, // 0..the largest sample in view, so spikes stay on screen
+}
+
+pub struct TimeSeries {
+    pub name: String,
+    pub color: [f32; 3],
+    pub scale: Scale,
+    samples: VecDeque<f32>,
+}
+
+impl TimeSeries {
+    pub fn latest(&self) -> Option<f32> {
+        self.samples.back().copied()
+    }
+
+    pub fn range(&self) -> (f32, f32) {
+        match self.scale {
+            Scale::Fixed(min, max) => (min, max),
+            Scale::Auto => {
+                let max = self.samples.iter().copied().fold(0.0, f32::max);
+                (0.0, if max > 0.0 { max } else { 1.0 })
+            },
+        }
+    }
+}
+
+// named time series plotted as line graphs into a lingering framebuffer,
+// which is then drawn as a quad over the top right of the screen
+pub struct MetricsOverlay {
+    pub series: Vec<TimeSeries>,
+    pub visible: bool,
+    size: f32, // of the quad on screen, in ndc
+    framebuffer: LingeringFramebuffer,
+    line_shader: ShaderWatcher,
+    quad_shader: ShaderWatcher,
+    vao: u32,
+    vbo: u32,
+}
+
+impl MetricsOverlay {
+    pub unsafe fn new(size: f32, resolution: i32) -> Result<Self, ShaderError> {
+        let quad_shader = ShaderWatcher::new("lingering", LINGERING_SHADER_VS, LINGERING_SHADER_FS, Defines::new())?;
+        let line_shader = ShaderWatcher::new("metrics", METRICS_SHADER_VS, METRICS_SHADER_FS, Defines::new())?;
+        let framebuffer = LingeringFramebuffer::new(size, size, resolution, resolution, &quad_shader.shader);
+
+        let mut vao = 0;
+        let mut vbo = 0;
+        GenVertexArrays(1, &mut vao);
+        GenBuffers(1, &mut vbo);
+        BindVertexArray(vao);
+        BindBuffer(ARRAY_BUFFER, vbo);
+        EnableVertexAttribArray(0);
+        VertexAttribPointer(0, 2, FLOAT, FALSE, 2 * size_of::<f32>() as GLsizei, std::ptr::null());
+        BindVertexArray(0);
+
+        Ok(Self {
+            series: Vec::new(),
+            visible: false,
+            size,
+            framebuffer,
+            line_shader,
+            quad_shader,
+            vao,
+            vbo,
+        })
+    }
+
+    pub fn shaders(&mut self) -> [&mut ShaderWatcher; 2] {
+        [&mut self.line_shader, &mut self.quad_shader]
+    }
+
+    pub fn add_series(&mut self, name: &str, color: [f32; 3], scale: Scale) {
+        self.series.push(TimeSeries {
+            name: name.to_string(),
+            color,
+            scale,
+            samples: VecDeque::with_capacity(HISTORY),
+        });
+    }
+
+    pub fn push(&mut self, name: &str, value: f32) {
+        let Some(series) = self.series.iter_mut().find(|s| s.name == name) else {
+            println!("ERROR::METRICS:: no series called {name}");
+            return;
+        };
+
+        if series.samples.len() == HISTORY {
+            series.samples.pop_front();
+        }
+        series.samples.push_back(value);
+    }
+
+    // where a sample lands inside the graph area, in the framebuffer's ndc
+    fn plot_point(index: usize, value: f32, (min, max): (f32, f32)) -> [f32; 2] {
+        let x = index as f32 / (HISTORY - 1) as f32;
+        let y = ((value - min) / (max - min)).clamp(0.0, 1.0);
+        let span = 2.0 - PADDING * 2.0;
+
+        [-1.0 + PADDING + x * span, -1.0 + PADDING + y * span]
+    }
+
+    // the quad spans 0..size of the screen's ndc on both axes, text wants window pixels
+    fn to_window_pixels(&self, [x, y]: [f32; 2], window_width: i32, window_height: i32) -> (f32, f32) {
+        let screen_x = (x + 1.0) * 0.5 * self.size;
+        let screen_y = (y + 1.0) * 0.5 * self.size;
+
+        ((screen_x + 1.0) * 0.5 * window_width as f32, (1.0 - screen_y) * 0.5 * window_height as f32)
+    }
+
+    unsafe fn draw_vertices(&self, vertices: &[[f32; 2]], mode: GLenum, color: [f32; 4]) {
+        if vertices.is_empty() {
+            return;
+        }
+
+        BindBuffer(ARRAY_BUFFER, self.vbo);
+        BufferData(
+            ARRAY_BUFFER,
+            size_of_val(vertices) as GLsizeiptr,
+            vertices.as_ptr() as *const c_void,
+            STREAM_DRAW,
+        );
+
+        self.line_shader.shader.set_uniform("color", color);
+        DrawArrays(mode, 0, vertices.len() as GLsizei);
+    }
+
+    pub unsafe fn draw(&self, window_width: i32, window_height: i32, text: &mut TextRenderer) {
+        if !self.visible {
+            return;
+        }
+
+        self.framebuffer.bind();
+        Disable(DEPTH_TEST);
+        ClearColor(0.05, 0.05, 0.08, 1.0);
+        Clear(COLOR_BUFFER_BIT);
+
+        self.line_shader.shader.use_shader();
+        BindVertexArray(self.vao);
+
+        // scale lines at the bottom, middle and top of the range
+        let grid: Vec<[f32; 2]> = [0.0, 0.5, 1.0].iter()
+            .flat_map(|t| [Self::plot_point(0, *t, (0.0, 1.0)), Self::plot_point(HISTORY - 1, *t, (0.0, 1.0))])
+            .collect();
+        self.draw_vertices(&grid, LINES, [0.3, 0.3, 0.35, 1.0]);
+
+        for (i, series) in self.series.iter().enumerate() {
+            let range = series.range();
+            let line: Vec<[f32; 2]> = series.samples.iter()
+                .enumerate()
+                .map(|(j, v)| Self::plot_point(j + HISTORY - series.samples.len(), *v, range))
+                .collect();
+            let [r, g, b] = series.color;
+            self.draw_vertices(&line, LINE_STRIP, [r, g, b, 1.0]);
+
+            // legend swatch, one row per series in the top left
+            let x = -1.0 + PADDING;
+            let y = 1.0 - PADDING - i as f32 * 0.08;
+            let swatch = [[x, y], [x + 0.06, y], [x + 0.06, y - 0.05], [x, y], [x + 0.06, y - 0.05], [x, y - 0.05]];
+            self.draw_vertices(&swatch, TRIANGLES, [r, g, b, 1.0]);
+        }
+
+        BindVertexArray(0);
+        BindFramebuffer(FRAMEBUFFER, 0);
+        Viewport(0, 0, window_width, window_height);
+
+        Enable(BLEND);
+        BlendFunc(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+        self.quad_shader.shader.use_shader();
+        self.quad_shader.shader.set_uniform("screenTexture", 0);
+        self.quad_shader.shader.set_uniform("opacity", 0.85);
+        ActiveTexture(TEXTURE0);
+        self.framebuffer.draw(&self.quad_shader.shader, 1.0, 1.0);
+        Disable(BLEND);
+        Enable(DEPTH_TEST);
+
+        // labels next to the swatches, queued for the caller's next text flush
+        for (i, series) in self.series.iter().enumerate() {
+            let (x, y) = self.to_window_pixels(
+                [-1.0 + PADDING + 0.08, 1.0 - PADDING - i as f32 * 0.08],
+                window_width,
+                window_height,
+            );
+            // every series has its own range, the grid lines only mean something next to it
+            let (min, max) = series.range();
+            let label = format!("{} {:.1} ({min:.1}..{max:.1})", series.name, series.latest().unwrap_or(0.0));
+            let [r, g, b] = series.color;
+            text.queue(&label, x, y, 1.0, [r, g, b, 1.0]);
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use cgmath::{vec3, InnerSpace, Vector3, Zero};
+
+const GRAVITY: f32 = 28.0;
+const TERMINAL_VELOCITY: f32 = 60.0;
+const JUMP_VELOCITY: f32 = 9.0;
+pub const WALK_SPEED: f32 = 4.3; // blocks per second, the default for `Player::walk_speed`
+const SPRINT_MULTIPLIER: f32 = 1.6;
+const STEP_HEIGHT: f32 = 1.0; // walk up single voxels without jumping
+const MAX_TIMESTEP: f32 = 1.0 / 120.0;
+const SKIN: f32 = 0.001; // gap kept between the box and the voxels it touches
+
+// what the physics needs to know about the world, implemented by `World`
+// and by plain chunk maps so it can be exercised without a window
+pub trait VoxelQuery {
+    fn is_solid(&self, pos: Vector3<i32>) -> bool;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    pub fn translate(&self, offset: Vector3<f32>) -> Self {
+        Self {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+
+    // inclusive range of voxel cells the box overlaps on one axis
+    fn cells(&self, axis: usize) -> (i32, i32) {
+        (self.min[axis].floor() as i32, self.max[axis].ceil() as i32 - 1)
+    }
+
+    pub fn intersects_world(&self, world: &impl VoxelQuery) -> bool {
+        let (x0, x1) = self.cells(0);
+        let (y0, y1) = self.cells(1);
+        let (z0, z1) = self.cells(2);
+
+        (x0..=x1).any(|x| (y0..=y1).any(|y| (z0..=z1).any(|z| world.is_solid(vec3(x, y, z)))))
+    }
+
+    // is there a solid voxel in the slice of cells at `layer` along `axis`, under the box's other two axes
+    fn slice_is_solid(&self, axis: usize, layer: i32, world: &impl VoxelQuery) -> bool {
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let (a0, a1) = self.cells(a);
+        let (b0, b1) = self.cells(b);
+
+        (a0..=a1).any(|i| (b0..=b1).any(|j| {
+            let mut cell = vec3(0, 0, 0);
+            cell[axis] = layer;
+            cell[a] = i;
+            cell[b] = j;
+            world.is_solid(cell)
+        }))
+    }
+
+    // how far the box can travel along `axis` (up to `delta`) before it touches a solid voxel
+    pub fn sweep(&self, axis: usize, delta: f32, world: &impl VoxelQuery) -> f32 {
+        if delta > 0.0 {
+            let first = (self.max[axis] - SKIN).ceil() as i32;
+            let last = (self.max[axis] + delta).ceil() as i32 - 1;
+
+            for layer in first..=last {
+                if self.slice_is_solid(axis, layer, world) {
+                    return (layer as f32 - self.max[axis] - SKIN).clamp(0.0, delta);
+                }
+            }
+        } else if delta < 0.0 {
+            let first = (self.min[axis] + SKIN).floor() as i32 - 1;
+            let last = (self.min[axis] + delta).floor() as i32;
+
+            for layer in (last..=first).rev() {
+                if self.slice_is_solid(axis, layer, world) {
+                    return (layer as f32 + 1.0 - self.min[axis] + SKIN).clamp(delta, 0.0);
+                }
+            }
+        }
+
+        delta
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PlayerInput {
+    pub wish_dir: Vector3<f32>, // horizontal, zero when standing still
+    pub jump: bool,
+    pub sprint: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Player {
+    pub pos: Vector3<f32>, // bottom center of the box
+    pub velocity: Vector3<f32>,
+    pub half_width: f32,
+    pub height: f32,
+    pub eye_height: f32,
+    pub on_ground: bool,
+    pub walk_speed: f32, // blocks per second, before sprinting
+}
+
+impl Player {
+    pub fn new(pos: Vector3<f32>) -> Self {
+        Self {
+            pos,
+            velocity: Vector3::zero(),
+            half_width: 0.3,
+            height: 1.8,
+            eye_height: 1.62,
+            on_ground: false,
+            walk_speed: WALK_SPEED,
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(
+            self.pos - vec3(self.half_width, 0.0, self.half_width),
+            self.pos + vec3(self.half_width, self.height, self.half_width),
+        )
+    }
+
+    pub fn eye_position(&self) -> Vector3<f32> {
+        self.pos + vec3(0.0, self.eye_height, 0.0)
+    }
+
+    pub fn set_eye_position(&mut self, eye: Vector3<f32>) {
+        self.pos = eye - vec3(0.0, self.eye_height, 0.0);
+        self.velocity = Vector3::zero();
+        self.on_ground = false;
+    }
+
+    // when walking mode is switched on inside the terrain, climb out instead of getting stuck
+    pub fn unstick(&mut self, world: &impl VoxelQuery) {
+        for _ in 0..64 {
+            if !self.aabb().intersects_world(world) {
+                return;
+            }
+            self.pos.y = self.pos.y.floor() + 1.0 + SKIN;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, input: PlayerInput, world: &impl VoxelQuery) {
+        let mut remaining = dt.min(0.25); // don't try to catch up on a frozen window
+        while remaining > 0.0 {
+            let step = remaining.min(MAX_TIMESTEP);
+            self.step(step, input, world);
+            remaining -= step;
+        }
+    }
+
+    fn step(&mut self, dt: f32, input: PlayerInput, world: &impl VoxelQuery) {
+        let mut speed = self.walk_speed;
+        if input.sprint {
+            speed *= SPRINT_MULTIPLIER;
+        }
+
+        let wish = if input.wish_dir.magnitude2() > 0.0 {
+            vec3(input.wish_dir.x, 0.0, input.wish_dir.z).normalize() * speed
+        } else {
+            Vector3::zero()
+        };
+        self.velocity.x = wish.x;
+        self.velocity.z = wish.z;
+
+        if input.jump && self.on_ground {
+            self.velocity.y = JUMP_VELOCITY;
+            self.on_ground = false;
+        }
+        self.velocity.y = (self.velocity.y - GRAVITY * dt).max(-TERMINAL_VELOCITY);
+
+        self.move_and_collide(self.velocity * dt, world);
+    }
+
+    fn move_and_collide(&mut self, delta: Vector3<f32>, world: &impl VoxelQuery) {
+        let mut aabb = self.aabb();
+
+        let dy = aabb.sweep(1, delta.y, world);
+        aabb = aabb.translate(vec3(0.0, dy, 0.0));
+        if dy != delta.y {
+            self.velocity.y = 0.0;
+        }
+        self.on_ground = delta.y < 0.0 && dy > delta.y;
+
+        let (mut moved, mut moved_by) = move_horizontal(aabb, delta, world);
+
+        let blocked = moved_by.x != delta.x || moved_by.z != delta.z;
+        if blocked && self.on_ground {
+            let up = aabb.sweep(1, STEP_HEIGHT, world);
+            let (stepped, stepped_by) = move_horizontal(aabb.translate(vec3(0.0, up, 0.0)), delta, world);
+            let down = stepped.sweep(1, -up, world);
+
+            if stepped_by.magnitude2() > moved_by.magnitude2() {
+                moved = stepped.translate(vec3(0.0, down, 0.0));
+                moved_by = stepped_by;
+            }
+        }
+
+        if moved_by.x != delta.x {
+            self.velocity.x = 0.0;
+        }
+        if moved_by.z != delta.z {
+            self.velocity.z = 0.0;
+        }
+
+        self.pos = vec3(
+            (moved.min.x + moved.max.x) * 0.5,
+            moved.min.y,
+            (moved.min.z + moved.max.z) * 0.5,
+        );
+    }
+}
+
+// x then z, returns the moved box and how far it actually went on each axis
+fn move_horizontal(aabb: Aabb, delta: Vector3<f32>, world: &impl VoxelQuery) -> (Aabb, Vector3<f32>) {
+    let dx = aabb.sweep(0, delta.x, world);
+    let aabb = aabb.translate(vec3(dx, 0.0, 0.0));
+    let dz = aabb.sweep(2, delta.z, world);
+    let aabb = aabb.translate(vec3(0.0, 0.0, dz));
+
+    (aabb, vec3(dx, 0.0, dz))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::world::{Chunk, Voxel};
+
+    const STILL: PlayerInput = PlayerInput { wish_dir: vec3(0.0, 0.0, 0.0), jump: false, sprint: false };
+
+    // one chunk at the origin with a floor along y = 0
+    fn floor() -> HashMap<Vector3<i32>, Chunk> {
+        let mut chunk = Chunk::empty(vec3(0.0, 0.0, 0.0));
+        for x in 0..24 {
+            for z in 0..24 {
+                chunk.set_voxel(x, 0, z, Voxel::ground());
+            }
+        }
+
+        HashMap::from([(vec3(0, 0, 0), chunk)])
+    }
+
+    fn set(world: &mut HashMap<Vector3<i32>, Chunk>, x: usize, y: usize, z: usize) {
+        world.get_mut(&vec3(0, 0, 0)).unwrap().set_voxel(x, y, z, Voxel::ground());
+    }
+
+    fn walk(dir: Vector3<f32>) -> PlayerInput {
+        PlayerInput { wish_dir: dir, ..STILL }
+    }
+
+    fn run(player: &mut Player, input: PlayerInput, seconds: f32, world: &HashMap<Vector3<i32>, Chunk>) {
+        for _ in 0..(seconds * 60.0) as usize {
+            player.update(1.0 / 60.0, input, world);
+        }
+    }
+
+    #[test]
+    fn lands_on_the_floor() {
+        let world = floor();
+        let mut player = Player::new(vec3(12.5, 6.0, 12.5));
+        run(&mut player, STILL, 2.0, &world);
+
+        assert!(player.on_ground);
+        assert!((player.pos.y - 1.0).abs() < 0.01, "{}", player.pos.y);
+        assert_eq!(player.velocity.y, 0.0);
+    }
+
+    #[test]
+    fn walls_stop_movement_on_every_axis() {
+        let mut world = floor();
+        for y in 1..4 {
+            for i in 0..24 {
+                set(&mut world, 8, y, i);
+                set(&mut world, 16, y, i);
+                set(&mut world, i, y, 8);
+                set(&mut world, i, y, 16);
+            }
+        }
+
+        for (dir, axis, limit) in [
+            (vec3(1.0, 0.0, 0.0), 0, 16.0 - 0.3),
+            (vec3(-1.0, 0.0, 0.0), 0, 9.0 + 0.3),
+            (vec3(0.0, 0.0, 1.0), 2, 16.0 - 0.3),
+            (vec3(0.0, 0.0, -1.0), 2, 9.0 + 0.3),
+        ] {
+            let mut player = Player::new(vec3(12.5, 1.0, 12.5));
+            run(&mut player, walk(dir), 3.0, &world);
+            assert!((player.pos[axis] - limit).abs() < 0.01, "{dir:?}: {}", player.pos[axis]);
+            assert!(!player.aabb().intersects_world(&world));
+        }
+
+        // and the ceiling stops a jump
+        set(&mut world, 12, 3, 12);
+        let mut player = Player::new(vec3(12.5, 1.0, 12.5));
+        player.on_ground = true;
+        run(&mut player, PlayerInput { jump: true, ..STILL }, 0.2, &world);
+        assert!(player.aabb().max.y <= 3.0 + 1e-3, "{}", player.aabb().max.y);
+    }
+
+    #[test]
+    fn steps_up_one_block_but_not_two() {
+        let mut world = floor();
+        for x in 14..24 {
+            for z in 0..24 {
+                set(&mut world, x, 1, z);
+            }
+        }
+        let mut player = Player::new(vec3(12.5, 1.0, 12.5));
+        run(&mut player, STILL, 0.1, &world);
+        run(&mut player, walk(vec3(1.0, 0.0, 0.0)), 0.6, &world);
+        assert!(player.pos.x > 14.5 && (player.pos.y - 2.0).abs() < 0.01, "{:?}", player.pos);
+
+        for z in 0..24 {
+            set(&mut world, 17, 2, z);
+            set(&mut world, 17, 3, z);
+        }
+        run(&mut player, walk(vec3(1.0, 0.0, 0.0)), 1.5, &world);
+        assert!((player.pos.x - (17.0 - 0.3)).abs() < 0.01, "{:?}", player.pos);
+        assert!((player.pos.y - 2.0).abs() < 0.01, "{:?}", player.pos);
+    }
+
+    #[test]
+    fn fast_boxes_do_not_tunnel() {
+        let mut world = floor();
+        for y in 1..4 {
+            for z in 0..24 {
+                set(&mut world, 20, y, z);
+            }
+        }
+
+        // a single long sweep stops at the first wall
+        let aabb = Player::new(vec3(12.5, 1.0, 12.5)).aabb();
+        assert!((aabb.sweep(0, 50.0, &world) - (20.0 - 12.8)).abs() < 0.01);
+
+        // falling at terminal velocity with a huge frame still ends up on the one block thick floor
+        let mut player = Player::new(vec3(12.5, 20.0, 12.5));
+        player.velocity.y = -TERMINAL_VELOCITY;
+        player.update(0.25, STILL, &world);
+        player.update(0.25, STILL, &world);
+        assert!(player.on_ground);
+        assert!((player.pos.y - 1.0).abs() < 0.01, "{}", player.pos.y);
+    }
+}
@@ -0,0 +1,299 @@
+use std::ptr;
+
+use cgmath::vec2;
+use gl::*;
+use gl::types::*;
+
+use crate::{glsl::Defines, shader::{Shader, ShaderError}, shader_watcher::ShaderWatcher, shaders::*};
+
+const HDR_FORMAT: GLenum = RGBA16F; // so bloom and tone mapping have something above 1.0 to work with
+const BLOOM_PASSES: usize = 2; // horizontal + vertical blurs
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostPass {
+    MotionBlur,
+    Fxaa,
+    Bloom,
+    ToneMap,
+}
+
+impl PostPass {
+    pub const ALL: [PostPass; 4] = [PostPass::MotionBlur, PostPass::Bloom, PostPass::ToneMap, PostPass::Fxaa];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostPass::MotionBlur => "motion_blur",
+            PostPass::Fxaa => "fxaa",
+            PostPass::Bloom => "bloom",
+            PostPass::ToneMap => "tone_map",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|pass| pass.name() == name)
+    }
+}
+
+// a texture to render into, with a depth buffer if the scene is drawn there
+pub struct RenderTarget {
+    pub fbo: u32,
+    pub texture: u32,
+    depth: Option<u32>,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl RenderTarget {
+    pub unsafe fn new(width: i32, height: i32, with_depth: bool) -> Self {
+        let mut fbo = 0;
+        GenFramebuffers(1, &mut fbo);
+        BindFramebuffer(FRAMEBUFFER, fbo);
+
+        let mut texture = 0;
+        GenTextures(1, &mut texture);
+        BindTexture(TEXTURE_2D, texture);
+        TexImage2D(TEXTURE_2D, 0, HDR_FORMAT as GLint, width, height, 0, RGBA, FLOAT, ptr::null());
+        TexParameteri(TEXTURE_2D, TEXTURE_MIN_FILTER, LINEAR as GLint);
+        TexParameteri(TEXTURE_2D, TEXTURE_MAG_FILTER, LINEAR as GLint);
+        TexParameteri(TEXTURE_2D, TEXTURE_WRAP_S, CLAMP_TO_EDGE as GLint);
+        TexParameteri(TEXTURE_2D, TEXTURE_WRAP_T, CLAMP_TO_EDGE as GLint);
+        FramebufferTexture2D(FRAMEBUFFER, COLOR_ATTACHMENT0, TEXTURE_2D, texture, 0);
+
+        let depth = with_depth.then(|| {
+            let mut rbo = 0;
+            GenRenderbuffers(1, &mut rbo);
+            BindRenderbuffer(RENDERBUFFER, rbo);
+            RenderbufferStorage(RENDERBUFFER, DEPTH24_STENCIL8, width, height);
+            FramebufferRenderbuffer(FRAMEBUFFER, DEPTH_STENCIL_ATTACHMENT, RENDERBUFFER, rbo);
+            rbo
+        });
+
+        if CheckFramebufferStatus(FRAMEBUFFER) != FRAMEBUFFER_COMPLETE {
+            println!("ERROR::FRAMEBUFFER:: post processing target {width}x{height} is not complete!");
+        }
+        BindFramebuffer(FRAMEBUFFER, 0);
+
+        Self { fbo, texture, depth, width, height }
+    }
+
+    pub unsafe fn bind(&self) {
+        BindFramebuffer(FRAMEBUFFER, self.fbo);
+        Viewport(0, 0, self.width, self.height);
+    }
+
+    pub unsafe fn delete(&mut self) {
+        DeleteFramebuffers(1, &self.fbo);
+        DeleteTextures(1, &self.texture);
+        if let Some(rbo) = self.depth {
+            DeleteRenderbuffers(1, &rbo);
+        }
+    }
+
+    pub unsafe fn resize(&mut self, width: i32, height: i32) {
+        if (width, height) != (self.width, self.height) {
+            let with_depth = self.depth.is_some();
+            self.delete();
+            *self = Self::new(width, height, with_depth);
+        }
+    }
+}
+
+// the scene is drawn into an offscreen target between `begin` and `finish`, then runs through
+// the enabled passes in order before landing on the screen. overlays are drawn after `finish`
+pub struct PostProcessor {
+    pub passes: Vec<(PostPass, bool)>,
+    pub motion_blur_strength: f32, // how much of last frame is kept, 0..1
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    pub exposure: f32,
+
+    scene: RenderTarget,
+    targets: [RenderTarget; 2], // ping-ponged between passes
+    history: RenderTarget, // motion blur's output from the previous frame
+    has_history: bool,
+    bloom: [RenderTarget; 2], // half resolution
+
+    copy_shader: ShaderWatcher,
+    motion_blur_shader: ShaderWatcher,
+    fxaa_shader: ShaderWatcher,
+    bloom_extract_shader: ShaderWatcher,
+    blur_shader: ShaderWatcher,
+    bloom_combine_shader: ShaderWatcher,
+    tone_map_shader: ShaderWatcher,
+    vao: u32,
+}
+
+impl PostProcessor {
+    // `enabled` is run in the order given, the passes left out come after it switched off
+    pub unsafe fn new(width: i32, height: i32, enabled: &[PostPass]) -> Result<Self, ShaderError> {
+        let passes = enabled.iter()
+            .map(|pass| (*pass, true))
+            .chain(PostPass::ALL.into_iter().filter(|pass| !enabled.contains(pass)).map(|pass| (pass, false)))
+            .collect();
+
+        let mut vao = 0;
+        GenVertexArrays(1, &mut vao);
+        let pass = |fs_file, fs_fallback| {
+            ShaderWatcher::from_files("fullscreen.vert", fs_file, FULLSCREEN_SHADER_VS, fs_fallback, Defines::new())
+        };
+
+        Ok(Self {
+            passes,
+            motion_blur_strength: 0.6,
+            bloom_threshold: 1.0, // only the sun and anything else past white
+            bloom_intensity: 0.6,
+            exposure: 1.0,
+
+            scene: RenderTarget::new(width, height, true),
+            targets: [RenderTarget::new(width, height, false), RenderTarget::new(width, height, false)],
+            history: RenderTarget::new(width, height, false),
+            has_history: false,
+            bloom: [RenderTarget::new(width / 2, height / 2, false), RenderTarget::new(width / 2, height / 2, false)],
+
+            copy_shader: pass("copy.frag", COPY_SHADER_FS)?,
+            motion_blur_shader: pass("motion_blur.frag", MOTION_BLUR_SHADER_FS)?,
+            fxaa_shader: pass("fxaa.frag", FXAA_SHADER_FS)?,
+            bloom_extract_shader: pass("bloom_extract.frag", BLOOM_EXTRACT_SHADER_FS)?,
+            blur_shader: pass("blur.frag", BLUR_SHADER_FS)?,
+            bloom_combine_shader: pass("bloom_combine.frag", BLOOM_COMBINE_SHADER_FS)?,
+            tone_map_shader: pass("tone_map.frag", TONE_MAP_SHADER_FS)?,
+            vao,
+        })
+    }
+
+    pub fn shaders(&mut self) -> [&mut ShaderWatcher; 7] {
+        [
+            &mut self.copy_shader,
+            &mut self.motion_blur_shader,
+            &mut self.fxaa_shader,
+            &mut self.bloom_extract_shader,
+            &mut self.blur_shader,
+            &mut self.bloom_combine_shader,
+            &mut self.tone_map_shader,
+        ]
+    }
+
+    pub fn is_enabled(&self, pass: PostPass) -> bool {
+        self.passes.iter().any(|(p, enabled)| *p == pass && *enabled)
+    }
+
+    // returns whether the pass is now on
+    pub fn toggle(&mut self, pass: PostPass) -> bool {
+        let Some((_, enabled)) = self.passes.iter_mut().find(|(p, _)| *p == pass) else {
+            return false;
+        };
+        *enabled = !*enabled;
+
+        if pass == PostPass::MotionBlur {
+            self.has_history = false; // don't blend in a frame from whenever it was last on
+        }
+        *enabled
+    }
+
+    // everything drawn until `finish` goes into the scene target
+    pub unsafe fn begin(&mut self, width: i32, height: i32) {
+        if (width, height) != (self.scene.width, self.scene.height) {
+            self.scene.resize(width, height);
+            for target in self.targets.iter_mut().chain([&mut self.history]) {
+                target.resize(width, height);
+            }
+            for target in &mut self.bloom {
+                target.resize(width / 2, height / 2);
+            }
+            self.has_history = false;
+        }
+
+        self.scene.bind();
+    }
+
+    // draws the fullscreen triangle into `target` with `inputs` bound to texture units in order
+    unsafe fn run(&self, shader: &Shader, target: Option<&RenderTarget>, inputs: &[(&str, u32)]) {
+        match target {
+            Some(target) => target.bind(),
+            None => {
+                BindFramebuffer(FRAMEBUFFER, 0);
+                Viewport(0, 0, self.scene.width, self.scene.height);
+            },
+        }
+
+        shader.use_shader();
+        for (unit, (name, texture)) in inputs.iter().enumerate() {
+            ActiveTexture(TEXTURE0 + unit as u32);
+            BindTexture(TEXTURE_2D, *texture);
+            shader.set_uniform(name, unit as i32);
+        }
+
+        DrawArrays(TRIANGLES, 0, 3);
+        ActiveTexture(TEXTURE0);
+    }
+
+    pub unsafe fn finish(&mut self) {
+        Disable(DEPTH_TEST);
+        BindVertexArray(self.vao);
+
+        let (width, height) = (self.scene.width as f32, self.scene.height as f32);
+        let mut input = self.scene.texture;
+        let mut next = 0;
+
+        for (pass, enabled) in self.passes.clone() {
+            if !enabled {
+                continue;
+            }
+            let out = &self.targets[next];
+
+            match pass {
+                PostPass::MotionBlur => {
+                    let previous = if self.has_history { self.history.texture } else { input };
+                    self.motion_blur_shader.shader.use_shader();
+                    self.motion_blur_shader.shader.set_uniform("strength", self.motion_blur_strength);
+                    self.run(&self.motion_blur_shader.shader, Some(out), &[("image", input), ("previous", previous)]);
+
+                    BindFramebuffer(READ_FRAMEBUFFER, out.fbo);
+                    BindFramebuffer(DRAW_FRAMEBUFFER, self.history.fbo);
+                    BlitFramebuffer(0, 0, out.width, out.height, 0, 0, out.width, out.height, COLOR_BUFFER_BIT, NEAREST);
+                    self.has_history = true;
+                },
+                PostPass::Fxaa => {
+                    self.fxaa_shader.shader.use_shader();
+                    self.fxaa_shader.shader.set_uniform("texelSize", vec2(1.0 / width, 1.0 / height));
+                    self.run(&self.fxaa_shader.shader, Some(out), &[("image", input)]);
+                },
+                PostPass::Bloom => {
+                    self.bloom_extract_shader.shader.use_shader();
+                    self.bloom_extract_shader.shader.set_uniform("threshold", self.bloom_threshold);
+                    self.run(&self.bloom_extract_shader.shader, Some(&self.bloom[0]), &[("image", input)]);
+
+                    let texel = vec2(1.0 / self.bloom[0].width as f32, 1.0 / self.bloom[0].height as f32);
+                    self.blur_shader.shader.use_shader();
+                    for _ in 0..BLOOM_PASSES {
+                        self.blur_shader.shader.set_uniform("direction", vec2(texel.x, 0.0));
+                        self.run(&self.blur_shader.shader, Some(&self.bloom[1]), &[("image", self.bloom[0].texture)]);
+                        self.blur_shader.shader.set_uniform("direction", vec2(0.0, texel.y));
+                        self.run(&self.blur_shader.shader, Some(&self.bloom[0]), &[("image", self.bloom[1].texture)]);
+                    }
+
+                    self.bloom_combine_shader.shader.use_shader();
+                    self.bloom_combine_shader.shader.set_uniform("intensity", self.bloom_intensity);
+                    self.run(&self.bloom_combine_shader.shader, Some(out), &[("image", input), ("bloom", self.bloom[0].texture)]);
+                },
+                PostPass::ToneMap => {
+                    self.tone_map_shader.shader.use_shader();
+                    self.tone_map_shader.shader.set_uniform("exposure", self.exposure);
+                    self.run(&self.tone_map_shader.shader, Some(out), &[("image", input)]);
+                },
+            }
+
+            input = out.texture;
+            next = 1 - next;
+        }
+
+        if !self.is_enabled(PostPass::MotionBlur) {
+            self.has_history = false;
+        }
+
+        self.run(&self.copy_shader.shader, None, &[("image", input)]);
+
+        BindVertexArray(0);
+        Enable(DEPTH_TEST);
+    }
+}
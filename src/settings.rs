@@ -0,0 +1,300 @@
+use std::{fs, path::Path};
+
+use crate::{config::{parse_line, ConfigError}, integrity::Detached, player::WALK_SPEED, post_process::PostPass};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub fov: f32, // vertical, in degrees
+    pub sensitivity: f32,
+    pub speed: f32, // flying, in blocks per second
+    pub walk_speed: f32, // walking, in blocks per second
+    pub window_width: u32,
+    pub window_height: u32,
+    pub vsync: bool,
+    pub clear_color: [f32; 3],
+    pub render_distance: f32, // in chunks
+    pub seed: u32, // for the terrain
+    pub fog: bool,
+    pub shadows: bool,
+    pub shadow_resolution: u32, // per cascade
+    pub day_length: f32, // in seconds
+    pub start_hour: f32,
+    pub detached_blocks: Detached, // what blocks cut off from the ground do
+    pub post_passes: Vec<PostPass>, // switched on at startup, in the order they run
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            fov: 70.0,
+            sensitivity: 0.001,
+            speed: 5.0,
+            walk_speed: WALK_SPEED,
+            window_width: 1000,
+            window_height: 1000,
+            vsync: false,
+            clear_color: [0.1, 0.2, 0.3],
+            render_distance: 2.0,
+            seed: 0,
+            fog: true,
+            shadows: true,
+            shadow_resolution: 2048,
+            day_length: 1200.0,
+            start_hour: 8.0,
+            detached_blocks: Detached::Fall,
+            post_passes: vec![PostPass::Bloom, PostPass::ToneMap, PostPass::Fxaa],
+        }
+    }
+}
+
+impl Settings {
+    // reads `path`, writing the defaults there on first run. bad values are reported
+    // and replaced by their default so a typo never keeps the game from starting
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            let settings = Self::default();
+            if let Err(e) = fs::write(path, settings.to_config_string()) {
+                println!("ERROR::SETTINGS:: could not write default settings to {}: {e}", path.display());
+            }
+            return settings;
+        }
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                println!("ERROR::SETTINGS:: could not read {}: {e}", path.display());
+                return Self::default();
+            }
+        };
+
+        let (settings, errors) = Self::parse(&text);
+        for e in errors {
+            println!("ERROR::SETTINGS:: {}: {e}", path.display());
+        }
+
+        settings
+    }
+
+    pub fn parse(text: &str) -> (Self, Vec<ConfigError>) {
+        let mut settings = Self::default();
+        let mut errors = Vec::new();
+
+        for (i, raw) in text.lines().enumerate() {
+            let entry = match parse_line(i + 1, raw) {
+                Ok(Some(entry)) => entry,
+                Ok(None) => continue,
+                Err(e) => {
+                    errors.push(e);
+                    continue;
+                },
+            };
+            let line = entry.line;
+            let value = entry.value.as_str();
+
+            let result = match entry.key.as_str() {
+                "fov" => parse_in_range(value, 1.0, 179.0).map(|v| settings.fov = v),
+                "sensitivity" => parse_in_range(value, 0.00001, 1.0).map(|v| settings.sensitivity = v),
+                "speed" => parse_in_range(value, 0.01, 1000.0).map(|v| settings.speed = v),
+                "walk_speed" => parse_in_range(value, 0.01, 100.0).map(|v| settings.walk_speed = v),
+                "window_width" => parse_in_range(value, 100.0, 16384.0).map(|v| settings.window_width = v as u32),
+                "window_height" => parse_in_range(value, 100.0, 16384.0).map(|v| settings.window_height = v as u32),
+                "vsync" => parse_bool(value).map(|v| settings.vsync = v),
+                "clear_color" => parse_color(value).map(|v| settings.clear_color = v),
+                "render_distance" => parse_in_range(value, 1.0, 16.0).map(|v| settings.render_distance = v),
+                "seed" => value.parse().map(|v| settings.seed = v).map_err(|_| format!("`{value}` is not a whole number from 0 to {}", u32::MAX)),
+                "fog" => parse_bool(value).map(|v| settings.fog = v),
+                "shadows" => parse_bool(value).map(|v| settings.shadows = v),
+                "shadow_resolution" => parse_in_range(value, 256.0, 8192.0).map(|v| settings.shadow_resolution = v as u32),
+                "day_length" => parse_in_range(value, 1.0, 86400.0).map(|v| settings.day_length = v),
+                "start_hour" => parse_in_range(value, 0.0, 23.99).map(|v| settings.start_hour = v),
+                "detached_blocks" => parse_detached(value).map(|v| settings.detached_blocks = v),
+                "post_passes" => parse_post_passes(value).map(|v| settings.post_passes = v),
+                key => Err(format!("unknown setting `{key}`")),
+            };
+
+            if let Err(message) = result {
+                errors.push(ConfigError::new(line, message));
+            }
+        }
+
+        (settings, errors)
+    }
+
+    pub fn to_config_string(&self) -> String {
+        let [r, g, b] = self.clear_color;
+
+        format!(
+            "# engine settings, delete this file to get the defaults back\n\
+             fov = {}\n\
+             sensitivity = {}\n\
+             # blocks per second when flying and when walking\n\
+             speed = {}\n\
+             walk_speed = {}\n\
+             window_width = {}\n\
+             window_height = {}\n\
+             vsync = {}\n\
+             clear_color = {r}, {g}, {b}\n\
+             # how many chunks around the camera are loaded\n\
+             render_distance = {}\n\
+             # the same seed always generates the same terrain\n\
+             seed = {}\n\
+             # fades the edge of the loaded world into the sky\n\
+             fog = {}\n\
+             # sun shadows, and the size of each of their cascades in texels\n\
+             shadows = {}\n\
+             shadow_resolution = {}\n\
+             # real seconds per in-game day, and the hour the game starts at\n\
+             day_length = {}\n\
+             start_hour = {}\n\
+             # what blocks no longer connected to the ground do: {}\n\
+             detached_blocks = {}\n\
+             # post processing passes in the order they run, the ones left out start switched off.\n\
+             # any of: {}\n\
+             post_passes = {}\n",
+            self.fov,
+            self.sensitivity,
+            self.speed,
+            self.walk_speed,
+            self.window_width,
+            self.window_height,
+            self.vsync,
+            self.render_distance,
+            self.seed,
+            self.fog,
+            self.shadows,
+            self.shadow_resolution,
+            self.day_length,
+            self.start_hour,
+            Detached::ALL.map(|detached| detached.name()).join(", "),
+            self.detached_blocks.name(),
+            PostPass::ALL.map(|pass| pass.name()).join(", "),
+            self.post_passes.iter().map(PostPass::name).collect::<Vec<_>>().join(", "),
+        )
+    }
+}
+
+fn parse_in_range(value: &str, min: f32, max: f32) -> Result<f32, String> {
+    let v: f32 = value.parse().map_err(|_| format!("`{value}` is not a number"))?;
+    if !(min..=max).contains(&v) {
+        return Err(format!("{v} is outside of {min}..={max}"));
+    }
+
+    Ok(v)
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "true" | "on" | "1" => Ok(true),
+        "false" | "off" | "0" => Ok(false),
+        _ => Err(format!("`{value}` is not true or false")),
+    }
+}
+
+fn parse_detached(value: &str) -> Result<Detached, String> {
+    Detached::from_name(value).ok_or_else(|| {
+        let names = Detached::ALL.map(|detached| detached.name()).join(", ");
+        format!("`{value}` is not one of {names}")
+    })
+}
+
+fn parse_post_passes(value: &str) -> Result<Vec<PostPass>, String> {
+    let mut passes = Vec::new();
+    for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let pass = PostPass::from_name(name).ok_or_else(|| format!("unknown post processing pass `{name}`"))?;
+        if passes.contains(&pass) {
+            return Err(format!("`{name}` is listed twice"));
+        }
+        passes.push(pass);
+    }
+
+    Ok(passes)
+}
+
+fn parse_color(value: &str) -> Result<[f32; 3], String> {
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    if parts.len() != 3 {
+        return Err(format!("expected `r, g, b`, got `{value}`"));
+    }
+
+    let mut color = [0.0; 3];
+    for (c, part) in color.iter_mut().zip(parts) {
+        *c = parse_in_range(part, 0.0, 1.0)?;
+    }
+
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_round_trip() {
+        let (settings, errors) = Settings::parse(&Settings::default().to_config_string());
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn changed_settings_round_trip() {
+        let settings = Settings {
+            fov: 90.0,
+            walk_speed: 6.5,
+            vsync: true,
+            clear_color: [0.5, 0.25, 1.0],
+            render_distance: 6.0,
+            seed: 12345,
+            detached_blocks: Detached::Report,
+            post_passes: vec![PostPass::Fxaa, PostPass::MotionBlur],
+            ..Settings::default()
+        };
+        let (parsed, errors) = Settings::parse(&settings.to_config_string());
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(parsed, settings);
+    }
+
+    #[test]
+    fn parses_values() {
+        let (settings, errors) = Settings::parse("fov = 100\nvsync = on\n# comment\n\nrender_distance = 4 # chunks\npost_passes =");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(settings.fov, 100.0);
+        assert!(settings.vsync);
+        assert_eq!(settings.render_distance, 4.0);
+        assert!(settings.post_passes.is_empty());
+    }
+
+    // every bad line is reported and falls back to the default, the good ones still apply
+    #[test]
+    fn bad_values_keep_their_defaults() {
+        let cases = [
+            ("fov = 500", "outside of"),
+            ("fov = wide", "not a number"),
+            ("speed = 0", "outside of"),
+            ("vsync = maybe", "not true or false"),
+            ("clear_color = 1, 2", ""),
+            ("render_distance = 0.5", "outside of"),
+            ("seed = -1", "whole number"),
+            ("detached_blocks = explode", "not one of"),
+            ("post_passes = bloom, bloom", "listed twice"),
+            ("post_passes = sharpen", "unknown post processing pass"),
+            ("flying = yes", "unknown setting"),
+        ];
+
+        for (line, message) in cases {
+            let (settings, errors) = Settings::parse(&format!("walk_speed = 6\n{line}"));
+            assert_eq!(errors.len(), 1, "{line}");
+            assert_eq!(errors[0].line, 2, "{line}");
+            assert!(errors[0].message.contains(message), "{line}: {}", errors[0].message);
+            assert_eq!(settings, Settings { walk_speed: 6.0, ..Settings::default() }, "{line}");
+        }
+    }
+
+    #[test]
+    fn malformed_lines_keep_the_rest() {
+        let (settings, errors) = Settings::parse("fov = 90\nthis is not a setting\n= 3\nvsync = true");
+        assert_eq!(errors.iter().map(|e| e.line).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(settings, Settings { fov: 90.0, vsync: true, ..Settings::default() });
+    }
+}
@@ -3,58 +3,253 @@ use gl::types::*;
 
 use cgmath::*;
 
-use std::ptr;
-use std::ffi::CStr;
+use crate::glsl::{load_include, preprocess, Defines};
 
-#[derive(Clone, Copy, Debug)]
+use std::{cell::RefCell, collections::{HashMap, HashSet}, ffi::{c_void, CString}, fmt, mem::size_of, rc::Rc};
+
+// uniform blocks every program gets bound to automatically at link time,
+// glsl 330 can't pick a binding point itself
+pub const CAMERA_BLOCK_BINDING: u32 = 0;
+pub const SKY_BLOCK_BINDING: u32 = 1;
+pub const SHADOW_BLOCK_BINDING: u32 = 2;
+pub const BLOCKS_BLOCK_BINDING: u32 = 3;
+const UNIFORM_BLOCKS: [(&str, u32); 4] = [
+    ("Camera", CAMERA_BLOCK_BINDING),
+    ("Sky", SKY_BLOCK_BINDING),
+    ("Shadows", SHADOW_BLOCK_BINDING),
+    ("Blocks", BLOCKS_BLOCK_BINDING),
+];
+
+// same for samplers of textures that stay bound to one unit for the whole frame,
+// the post processing and text units count up from 0 so these start high
+pub const SHADOW_MAP_UNIT: u32 = 8;
+pub const CHUNK_ORIGINS_UNIT: u32 = 9;
+pub const BLOCK_TEXTURES_UNIT: u32 = 10;
+const SAMPLER_UNITS: [(&str, u32); 3] = [
+    ("shadowMap", SHADOW_MAP_UNIT),
+    ("chunkOrigins", CHUNK_ORIGINS_UNIT),
+    ("blockTextures", BLOCK_TEXTURES_UNIT),
+];
+
+// clones share the location cache, so they can be handed around like the program id
+#[derive(Clone, Debug)]
 pub struct Shader {
     pub id: u32,
+    uniforms: Rc<UniformCache>,
 }
 
-impl Shader {
-    pub fn new(shader_type: GLenum, code: &str) -> Self {
-        unsafe {
-            let shader = CreateShader(shader_type);
-            let cstr = std::ffi::CString::new(code.as_bytes()).unwrap();
-            ShaderSource(shader, 1, &cstr.as_ptr(), std::ptr::null());
-            CompileShader(shader);
-            check_shader_error(shader);
+#[derive(Debug, Default)]
+struct UniformCache {
+    locations: HashMap<String, i32>, // every active uniform, resolved once after linking
+    missing: RefCell<HashSet<String>>, // already warned about
+}
+
+// anything that can be written to a uniform location with `Shader::set_uniform`
+pub trait Uniform {
+    unsafe fn set(&self, location: i32);
+}
+
+impl Uniform for f32 {
+    unsafe fn set(&self, location: i32) {
+        Uniform1f(location, *self);
+    }
+}
+
+impl Uniform for i32 {
+    unsafe fn set(&self, location: i32) {
+        Uniform1i(location, *self);
+    }
+}
+
+impl Uniform for bool {
+    unsafe fn set(&self, location: i32) {
+        Uniform1i(location, *self as i32);
+    }
+}
+
+impl Uniform for Vector2<f32> {
+    unsafe fn set(&self, location: i32) {
+        Uniform2f(location, self.x, self.y);
+    }
+}
+
+impl Uniform for Vector3<f32> {
+    unsafe fn set(&self, location: i32) {
+        Uniform3f(location, self.x, self.y, self.z);
+    }
+}
+
+impl Uniform for Vector4<f32> {
+    unsafe fn set(&self, location: i32) {
+        Uniform4f(location, self.x, self.y, self.z, self.w);
+    }
+}
+
+impl Uniform for Matrix4<f32> {
+    unsafe fn set(&self, location: i32) {
+        UniformMatrix4fv(location, 1, FALSE, self.as_ptr());
+    }
+}
+
+impl Uniform for [f32; 3] {
+    unsafe fn set(&self, location: i32) {
+        Uniform3fv(location, 1, self.as_ptr());
+    }
+}
+
+impl Uniform for [f32; 4] {
+    unsafe fn set(&self, location: i32) {
+        Uniform4fv(location, 1, self.as_ptr());
+    }
+}
+
+// a std140 uniform block shared by every program bound to the same binding point
+pub struct UniformBuffer {
+    pub id: u32,
+    pub binding: u32,
+    size: usize,
+}
+
+impl UniformBuffer {
+    pub unsafe fn new(binding: u32, size: usize) -> Self {
+        let mut id = 0;
+        GenBuffers(1, &mut id);
+        BindBuffer(UNIFORM_BUFFER, id);
+        BufferData(UNIFORM_BUFFER, size as GLsizeiptr, std::ptr::null(), DYNAMIC_DRAW);
+        BindBufferBase(UNIFORM_BUFFER, binding, id);
+        BindBuffer(UNIFORM_BUFFER, 0);
+
+        Self { id, binding, size }
+    }
+
+    // `offset` in bytes, following the block's std140 layout
+    pub unsafe fn write<T: Copy>(&self, offset: usize, data: &T) {
+        assert!(offset + size_of::<T>() <= self.size, "write past the end of a uniform buffer");
 
-            let id = CreateProgram();
-            AttachShader(id, shader);
-            LinkProgram(id);
-            check_shader_link_error(id);
+        BindBuffer(UNIFORM_BUFFER, self.id);
+        BufferSubData(UNIFORM_BUFFER, offset as GLintptr, size_of::<T>() as GLsizeiptr, data as *const T as *const c_void);
+        BindBuffer(UNIFORM_BUFFER, 0);
+    }
+}
 
-            DeleteShader(shader);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Geometry,
+    Link,
+}
 
-            Self { id }
+impl ShaderStage {
+    fn from_gl(shader_type: GLenum) -> Self {
+        match shader_type {
+            VERTEX_SHADER => ShaderStage::Vertex,
+            GEOMETRY_SHADER => ShaderStage::Geometry,
+            _ => ShaderStage::Fragment,
         }
     }
 
-    pub fn new_pipeline(vs_code: &str, fs_code: &str) -> Self {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShaderStage::Vertex => "vertex",
+            ShaderStage::Fragment => "fragment",
+            ShaderStage::Geometry => "geometry",
+            ShaderStage::Link => "link",
+        }
+    }
+}
+
+// a stage that failed to compile, or a program that failed to link, with everything the driver said
+#[derive(Clone, Debug)]
+pub struct ShaderError {
+    pub stage: ShaderStage,
+    pub log: String,
+    files: Vec<(String, String)>, // of the failing stage, see `glsl::Expanded`. empty for link errors
+}
+
+impl ShaderError {
+    // the source string and line a driver log line points at. drivers disagree on the format:
+    // `1(12) : error` (nvidia), `1:12(5): error` (mesa), `ERROR: 1:12: ...` (amd/intel)
+    fn source_line(log_line: &str) -> Option<(usize, usize)> {
+        let rest = log_line.trim_start_matches("ERROR: ").trim_start_matches("WARNING: ");
+        let file: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        let rest = rest[file.len()..].strip_prefix('(').or_else(|| rest[file.len()..].strip_prefix(':'))?;
+        let line: String = rest.chars().take_while(char::is_ascii_digit).collect();
+
+        Some((file.parse().ok()?, line.parse().ok()?))
+    }
+}
+
+impl fmt::Display for ShaderError {
+    // the log with the offending source line under each message
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} shader failed", self.stage.name())?;
+        if self.stage == ShaderStage::Link {
+            write!(f, " to link")?;
+        } else {
+            write!(f, " to compile")?;
+        }
+
+        for line in self.log.lines() {
+            write!(f, "\n{line}")?;
+            let Some((file, n)) = Self::source_line(line) else {
+                continue;
+            };
+            let Some((name, source)) = self.files.get(file) else {
+                continue;
+            };
+            if let Some(code) = n.checked_sub(1).and_then(|i| source.lines().nth(i)) {
+                // lines of the main file are just numbered, includes say which file they're from
+                match file {
+                    0 => write!(f, "\n{n:>5} | {}", code.trim())?,
+                    _ => write!(f, "\n{name}:{n} | {}", code.trim())?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+impl Shader {
+    pub fn new(shader_type: GLenum, code: &str) -> Result<Self, ShaderError> {
+        unsafe { link(&[compile_stage(shader_type, code, &Defines::new())?]) }
+    }
+
+    pub fn new_pipeline(vs_code: &str, fs_code: &str) -> Result<Self, ShaderError> {
+        Self::with_defines(vs_code, fs_code, &Defines::new())
+    }
+
+    // both stages go through the preprocessor with the same defines
+    pub fn with_defines(vs_code: &str, fs_code: &str, defines: &Defines) -> Result<Self, ShaderError> {
         unsafe {
-            let vs = CreateShader(VERTEX_SHADER);
-            let cstr = std::ffi::CString::new(vs_code.as_bytes()).unwrap();
-            ShaderSource(vs, 1, &cstr.as_ptr(), std::ptr::null());
-            CompileShader(vs);
-            check_shader_error(vs);
-
-            let fs = CreateShader(FRAGMENT_SHADER);
-            let cstr = std::ffi::CString::new(fs_code.as_bytes()).unwrap();
-            ShaderSource(fs, 1, &cstr.as_ptr(), std::ptr::null());
-            CompileShader(fs);
-            check_shader_error(fs);
-
-            let id = CreateProgram();
-            AttachShader(id, vs);
-            AttachShader(id, fs);
-            LinkProgram(id);
-            check_shader_link_error(id);
-
-            Self { id }
+            let vs = compile_stage(VERTEX_SHADER, vs_code, defines)?;
+            let fs = match compile_stage(FRAGMENT_SHADER, fs_code, defines) {
+                Ok(fs) => fs,
+                Err(e) => {
+                    DeleteShader(vs);
+                    return Err(e);
+                },
+            };
+
+            link(&[vs, fs])
+        }
+    }
+
+    // no program, for things that get their real shader later
+    pub fn empty() -> Self {
+        Self {
+            id: 0,
+            uniforms: Rc::default(),
         }
     }
 
+    pub unsafe fn delete(&self) {
+        DeleteProgram(self.id);
+    }
+
     pub unsafe fn use_shader(&self) {
         UseProgram(self.id);
     }
@@ -63,66 +258,138 @@ impl Shader {
 
     }
 
-    pub unsafe fn uniform_1f(&self, name: &CStr, val: f32) {
-        Uniform1f(GetUniformLocation(self.id, name.as_ptr()), val);
+    pub fn has_uniform(&self, name: &str) -> bool {
+        self.uniforms.locations.contains_key(name)
     }
 
-    pub unsafe fn uniform_1i(&self, name: &CStr, val: i32) {
-        Uniform1i(GetUniformLocation(self.id, name.as_ptr()), val);
+    // the program has to be in use. uniforms the compiler dropped or that were never declared
+    // are warned about once and skipped
+    pub unsafe fn set_uniform(&self, name: &str, value: impl Uniform) {
+        match self.uniforms.locations.get(name) {
+            Some(location) => value.set(*location),
+            None => {
+                if self.uniforms.missing.borrow_mut().insert(name.to_string()) {
+                    println!("WARNING::SHADER:: program {} has no active uniform `{name}`", self.id);
+                }
+            },
+        }
     }
+}
 
-    pub unsafe fn uniform_mat4fv(&self, name: &CStr, mat: &Matrix4<f32>) {
-        UniformMatrix4fv(
-            GetUniformLocation(self.id, name.as_ptr()), 
-            1, 
-            FALSE, 
-            mat.as_ptr()
-        );
-    }
+// the files are kept unexpanded in errors, the `#line`s from preprocessing make the driver's line numbers match them
+unsafe fn compile_stage(shader_type: GLenum, code: &str, defines: &Defines) -> Result<u32, ShaderError> {
+    let stage = ShaderStage::from_gl(shader_type);
+    let expanded = preprocess(code, defines, &load_include).map_err(|e| ShaderError {
+        stage,
+        log: e.to_string(),
+        files: Vec::new(),
+    })?;
+    let cstr = std::ffi::CString::new(expanded.code.as_bytes()).map_err(|e| ShaderError {
+        stage,
+        log: e.to_string(),
+        files: Vec::new(),
+    })?;
 
-    pub unsafe fn uniform_vec3f(&self, name: &CStr, vec: &Vector3<f32>) {
-        Uniform3f(
-            GetUniformLocation(self.id, name.as_ptr()),
-            vec.x, vec.y, vec.z
-        );
-    }
-}
+    let shader = CreateShader(shader_type);
+    ShaderSource(shader, 1, &cstr.as_ptr(), std::ptr::null());
+    CompileShader(shader);
 
-pub unsafe fn check_shader_error(shader: u32) {
     let mut success = gl::FALSE as GLint;
-    let mut info_log = Vec::with_capacity(512);
-    info_log.set_len(54); // skip the trailing null char
     GetShaderiv(shader, COMPILE_STATUS, &mut success);
     if success != gl::TRUE as GLint {
-        GetShaderInfoLog(
-            shader,
-            512,
-            ptr::null_mut(),
-            info_log.as_mut_ptr() as *mut GLchar,
-        );
-        println!(
-            "ERROR::SHADER::COMPILATION::FAILED\n{}",
-            std::str::from_utf8(&info_log).unwrap()
-        );
+        let log = info_log(shader, GetShaderiv, GetShaderInfoLog);
+        DeleteShader(shader);
+        return Err(ShaderError { stage, log, files: expanded.files });
     }
+
+    Ok(shader)
 }
 
-pub unsafe fn check_shader_link_error(program: u32) {
+// links the compiled stages into a program, the stages are deleted either way
+unsafe fn link(stages: &[u32]) -> Result<Shader, ShaderError> {
+    let id = CreateProgram();
+    for stage in stages {
+        AttachShader(id, *stage);
+    }
+    LinkProgram(id);
+    for stage in stages {
+        DeleteShader(*stage);
+    }
+
     let mut success = gl::FALSE as GLint;
-    let mut info_log = Vec::with_capacity(512);
-    gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
+    GetProgramiv(id, LINK_STATUS, &mut success);
     if success != gl::TRUE as GLint {
-        gl::GetProgramInfoLog(
-            program,
-            512,
-            ptr::null_mut(),
-            info_log.as_mut_ptr() as *mut GLchar,
-        );
-        println!(
-            "ERROR::SHADER::PROGRAM::COMPILATION_FAILED\n{}",
-            std::str::from_utf8(&info_log).unwrap()
-        );
+        let log = info_log(id, GetProgramiv, GetProgramInfoLog);
+        DeleteProgram(id);
+        return Err(ShaderError { stage: ShaderStage::Link, log, files: Vec::new() });
     }
+
+    // hook up whichever of the shared blocks this program declares
+    for (block, binding) in UNIFORM_BLOCKS {
+        let name = CString::new(block).unwrap();
+        let index = GetUniformBlockIndex(id, name.as_ptr());
+        if index != INVALID_INDEX {
+            UniformBlockBinding(id, index, binding);
+        }
+    }
+
+    let shader = Shader { id, uniforms: Rc::new(active_uniforms(id)) };
+    let samplers: Vec<_> = SAMPLER_UNITS.iter().filter(|(name, _)| shader.has_uniform(name)).collect();
+    if !samplers.is_empty() {
+        shader.use_shader();
+        for (name, unit) in samplers {
+            shader.set_uniform(name, *unit as i32);
+        }
+        UseProgram(0);
+    }
+
+    Ok(shader)
+}
+
+unsafe fn active_uniforms(program: u32) -> UniformCache {
+    let mut count = 0;
+    let mut max_len = 0;
+    GetProgramiv(program, ACTIVE_UNIFORMS, &mut count);
+    GetProgramiv(program, ACTIVE_UNIFORM_MAX_LENGTH, &mut max_len);
+
+    let mut locations = HashMap::new();
+    for i in 0..count as u32 {
+        let mut name = vec![0u8; max_len.max(1) as usize];
+        let (mut len, mut size, mut kind) = (0, 0, 0);
+        GetActiveUniform(program, i, max_len, &mut len, &mut size, &mut kind, name.as_mut_ptr() as *mut GLchar);
+        name.truncate(len.max(0) as usize);
+        let name = String::from_utf8_lossy(&name).to_string();
+
+        let c_name = CString::new(name.as_str()).unwrap();
+        let location = GetUniformLocation(program, c_name.as_ptr());
+        if location < 0 {
+            continue; // lives in a uniform block
+        }
+
+        // arrays are reported as `name[0]`, let them be set by their plain name too
+        if let Some(base) = name.strip_suffix("[0]") {
+            locations.insert(base.to_string(), location);
+        }
+        locations.insert(name, location);
+    }
+
+    UniformCache { locations, missing: RefCell::default() }
+}
+
+// the whole log, however long it is
+unsafe fn info_log(
+    id: u32,
+    get_iv: unsafe fn(GLuint, GLenum, *mut GLint),
+    get_log: unsafe fn(GLuint, GLsizei, *mut GLsizei, *mut GLchar),
+) -> String {
+    let mut len = 0;
+    get_iv(id, INFO_LOG_LENGTH, &mut len);
+    let mut log = vec![0u8; len.max(1) as usize];
+    let mut written = 0;
+    get_log(id, len, &mut written, log.as_mut_ptr() as *mut GLchar);
+    log.truncate(written.max(0) as usize);
+
+    String::from_utf8_lossy(&log).trim_end().to_string()
 }
 
 #[macro_export]
@@ -133,3 +400,45 @@ macro_rules! cstr{
             .as_c_str()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_source_lines_in_driver_logs() {
+        let cases = [
+            ("0(12) : error C0000: syntax error", Some((0, 12))),
+            ("2:7(5): error: `x' undeclared", Some((2, 7))),
+            ("ERROR: 1:30: 'y' : undeclared identifier", Some((1, 30))),
+            ("WARNING: 0:4: unused", Some((0, 4))),
+            ("error: linking failed", None),
+            ("(12) : error", None),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(ShaderError::source_line(line), expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn annotates_errors_in_included_files() {
+        let error = ShaderError {
+            stage: ShaderStage::Fragment,
+            log: "0:2(1): error: main\n1:1(3): error: include\n2:9(1): error: out of range".to_string(),
+            files: vec![
+                ("<main>".to_string(), "#version 330 core\nvoid main() {".to_string()),
+                ("sky.glsl".to_string(), "  vec3 sky;".to_string()),
+            ],
+        };
+
+        assert_eq!(error.to_string(), [
+            "fragment shader failed to compile",
+            "0:2(1): error: main",
+            "    2 | void main() {",
+            "1:1(3): error: include",
+            "sky.glsl:1 | vec3 sky;",
+            "2:9(1): error: out of range",
+        ].join("\n"));
+    }
+}
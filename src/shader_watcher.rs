@@ -0,0 +1,178 @@
+use std::{fs, path::{Path, PathBuf}, time::{Instant, SystemTime}};
+
+use crate::{glsl::{load_include, preprocess, Defines, ShaderVariants}, shader::{Shader, ShaderError}, shaders::SHADER_DIR};
+
+// a shader program built from a vertex/fragment file pair that rebuilds itself when either file,
+// or anything they include, changes. a program that fails to compile never replaces the one that's running
+pub struct ShaderWatcher {
+    pub shader: Shader, // built with `defines`
+    pub error: Option<String>, // from the last reload, cleared once one succeeds
+    defines: Defines,
+    variants: ShaderVariants,
+    vs_path: PathBuf,
+    fs_path: PathBuf,
+    modified: [Option<SystemTime>; 2],
+    includes: Vec<(PathBuf, Option<SystemTime>)>, // what the stages pulled in at the last reload
+    last_check: Instant,
+}
+
+impl ShaderWatcher {
+    // `name` picks `shaders/<name>.vert` and `shaders/<name>.frag`, the embedded copies are used
+    // until the files are readable
+    pub fn new(name: &str, vs_fallback: &str, fs_fallback: &str, defines: Defines) -> Result<Self, ShaderError> {
+        Self::from_files(&format!("{name}.vert"), &format!("{name}.frag"), vs_fallback, fs_fallback, defines)
+    }
+
+    // for stages that don't share a name, like the post processing passes on `fullscreen.vert`
+    pub fn from_files(
+        vs_file: &str,
+        fs_file: &str,
+        vs_fallback: &str,
+        fs_fallback: &str,
+        defines: Defines,
+    ) -> Result<Self, ShaderError> {
+        let vs_path = Path::new(SHADER_DIR).join(vs_file);
+        let fs_path = Path::new(SHADER_DIR).join(fs_file);
+
+        let mut watcher = Self {
+            shader: Shader::empty(),
+            error: None,
+            defines,
+            variants: ShaderVariants::new(vs_fallback, fs_fallback),
+            modified: [modified_time(&vs_path), modified_time(&fs_path)],
+            vs_path,
+            fs_path,
+            includes: Vec::new(),
+            last_check: Instant::now(),
+        };
+
+        if !watcher.reload() {
+            println!("ERROR::SHADER:: using the embedded {fs_file} shader");
+            watcher.shader = watcher.variants.get(&watcher.defines)?;
+            if watcher.modified.contains(&None) {
+                watcher.error = None; // not running from the repo, nothing to watch
+            }
+        }
+
+        Ok(watcher)
+    }
+
+    // the fragment file, which tells programs sharing a vertex shader apart
+    pub fn name(&self) -> String {
+        self.fs_path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+    }
+
+    // the same sources built with other defines, cached until the next successful reload.
+    // callers must ask again after `reload` returns true, the old programs are deleted
+    pub fn variant(&mut self, defines: &Defines) -> Result<Shader, ShaderError> {
+        self.variants.get(defines)
+    }
+
+    // returns true if a new program was swapped in
+    pub fn reload(&mut self) -> bool {
+        self.modified = [modified_time(&self.vs_path), modified_time(&self.fs_path)];
+
+        let read = |path: &Path| fs::read_to_string(path).map_err(|e| format!("could not read {}: {e}", path.display()));
+        let result = read(&self.vs_path)
+            .and_then(|vs| Ok((vs, read(&self.fs_path)?)))
+            .and_then(|(vs, fs)| {
+                // before building, so fixing a broken include triggers the next reload
+                self.includes = included_files(&[&vs, &fs]);
+                let mut variants = ShaderVariants::new(&vs, &fs);
+                let shader = variants.get(&self.defines).map_err(|e| e.to_string())?;
+                Ok((variants, shader))
+            });
+
+        match result {
+            Ok((variants, shader)) => {
+                unsafe { self.variants.delete(); }
+                self.variants = variants;
+                self.shader = shader;
+                self.error = None;
+                true
+            },
+            Err(e) => {
+                println!("ERROR::SHADER:: {} / {}: {e}", self.vs_path.display(), self.fs_path.display());
+                self.error = Some(e);
+                false
+            },
+        }
+    }
+
+    pub fn reload_if_changed(&mut self) -> bool {
+        if self.last_check.elapsed().as_secs_f32() < 1.0 {
+            return false;
+        }
+        self.last_check = Instant::now();
+
+        let modified = [modified_time(&self.vs_path), modified_time(&self.fs_path)];
+        let stages_changed = modified.iter().all(Option::is_some) && modified != self.modified;
+        let includes_changed = self.includes.iter().any(|(path, modified)| {
+            let now = modified_time(path);
+            now.is_some() && now != *modified
+        });
+        if stages_changed || includes_changed {
+            return self.reload();
+        }
+
+        false
+    }
+}
+
+// the files in `shaders/` that expanding `sources` reads. includes inside `#ifdef`s count too,
+// the preprocessor expands them whatever the defines
+fn included_files(sources: &[&str]) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut names: Vec<String> = sources.iter()
+        .filter_map(|source| preprocess(source, &Defines::new(), &load_include).ok())
+        .flat_map(|expanded| expanded.files.into_iter().skip(1).map(|(name, _)| name))
+        .collect();
+    names.sort();
+    names.dedup();
+
+    names.into_iter()
+        .map(|name| {
+            let path = Path::new(SHADER_DIR).join(name);
+            let modified = modified_time(&path);
+            (path, modified)
+        })
+        .collect()
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// polls every watcher, returns the name and error of the first one whose last reload failed.
+// components own their watchers and hand them over through a `shaders()` accessor. they read
+// `watcher.shader` on every draw instead of keeping a copy, so swapping the program is all a reload needs
+pub fn reload_all<'a>(watchers: impl IntoIterator<Item = &'a mut ShaderWatcher>) -> Option<(String, String)> {
+    let mut failed = None;
+    for watcher in watchers {
+        watcher.reload_if_changed();
+        if let (None, Some(error)) = (&failed, &watcher.error) {
+            failed = Some((watcher.name(), error.clone()));
+        }
+    }
+
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shaders::{MESH_SHADER_FS, MESH_SHADER_VS, OUTLINE_SHADER_FS, OUTLINE_SHADER_VS, TEXT_SHADER_FS, TEXT_SHADER_VS};
+
+    #[test]
+    fn watches_every_include() {
+        let names = |sources: &[&str]| -> Vec<String> {
+            included_files(sources).into_iter().map(|(path, _)| path.file_name().unwrap().to_string_lossy().into_owned()).collect()
+        };
+
+        assert_eq!(names(&[OUTLINE_SHADER_VS, OUTLINE_SHADER_FS]), ["camera.glsl"]);
+        assert!(names(&[TEXT_SHADER_VS, TEXT_SHADER_FS]).is_empty());
+        // watched once, however many places include it
+        let mesh = names(&[MESH_SHADER_VS, MESH_SHADER_FS]);
+        assert_eq!(mesh.iter().filter(|name| *name == "camera.glsl").count(), 1);
+        assert!(mesh.windows(2).all(|pair| pair[0] < pair[1]), "{mesh:?}");
+    }
+}
@@ -1,109 +1,35 @@
-pub const MESH_SHADER_VS: &str = r#"
-    #version 330
-    layout (location = 0) in vec3 aPos;
-    layout (location = 1) in vec3 aColor;
-
-    out vec3 outColor;
-    // layout (location = 3) in mat4 instanceMatrix;
-
-    uniform mat4 proj; 
-    uniform mat4 view;
-    uniform mat4 model;
-
-    uniform vec3 chunkPos;
-
-    void main() {
-        gl_Position = proj * view * vec4(aPos + chunkPos, 1.0);
-        outColor = aColor;
-    }
-"#;
-
-pub const MESH_SHADER_FS: &str = r#"
-    #version 330
-
-    out vec4 frag_color;
-
-    in vec3 outColor;
-
-    void main() {
-        frag_color = vec4(outColor, 1.0);
-    }
-"#;
-
-pub const LINGERING_SHADER_VS: &str = r#"
-    #version 330 core
-    layout (location = 0) in vec2 aPos;
-    layout (location = 1) in vec2 aTexCoords;
-
-    out vec2 TexCoords;
-
-    void main()
-    {
-        TexCoords = aTexCoords;
-        gl_Position = vec4(aPos.x, aPos.y, 0.0, 1.0); 
-    } 
-"#;
-
-pub const LINGERING_SHADER_FS: &str = r#"
-    #version 330 core
-    out vec4 FragColor;
-
-    in vec2 TexCoords;
-
-    uniform sampler2D screenTexture;
-
-    void main()
-    {
-        vec3 col = texture(screenTexture, TexCoords * 1.0).rgb;
-        FragColor = vec4(col, 0.1);
-    } 
-"#;
-
-pub const GRAPH_SHADER_VS: &str = r#"
-    #version 330 core
-    layout (location = 0) in vec3 aPos;
-
-    uniform float ofsx;
-    uniform float ofsy;
-
-    out float ypos;
-
-    void main()
-    {
-        ypos = ofsy;
-        gl_Position = vec4(aPos + vec3(ofsx, ofsy, 0), 1.0); 
-    }
-"#;
-
-pub const GRAPH_SHADER_FS: &str = r#"
-    #version 330 core
-    out vec4 FragColor;
-
-    in float ypos;
-    uniform float ofsx;
-
-    void main()
-    {
-        FragColor = vec4(ypos*ypos, ypos+.1, ofsx, 1);
-    } 
-"#;
-
-pub const CLR_GRAPH_SHADER_VS: &str = r#"
-    #version 330 core
-    layout (location = 0) in vec3 aPos;
-
-    void main()
-    {
-        gl_Position = vec4(aPos, 1.0); 
-    }
-"#;
-
-pub const CLR_GRAPH_SHADER_FS: &str = r#"
-    #version 330 core
-    out vec4 FragColor;
-
-    void main()
-    {
-        FragColor = vec4(0.1);
-    } 
-"#;
+// the glsl lives in /shaders so it can be edited and hot-reloaded while the game runs,
+// these embedded copies are what's used when the files can't be found
+pub const SHADER_DIR: &str = "shaders";
+
+// everything `#include`-able, by the name used in the directive
+pub const INCLUDES: [(&str, &str); 5] = [
+    ("chunk.glsl", include_str!("../shaders/chunk.glsl")),
+    ("voxel.glsl", include_str!("../shaders/voxel.glsl")),
+    ("camera.glsl", include_str!("../shaders/camera.glsl")),
+    ("sky.glsl", include_str!("../shaders/sky.glsl")),
+    ("shadows.glsl", include_str!("../shaders/shadows.glsl")),
+];
+
+pub const MESH_SHADER_VS: &str = include_str!("../shaders/mesh.vert");
+pub const MESH_SHADER_FS: &str = include_str!("../shaders/mesh.frag");
+pub const LINGERING_SHADER_VS: &str = include_str!("../shaders/lingering.vert");
+pub const LINGERING_SHADER_FS: &str = include_str!("../shaders/lingering.frag");
+pub const METRICS_SHADER_VS: &str = include_str!("../shaders/metrics.vert");
+pub const METRICS_SHADER_FS: &str = include_str!("../shaders/metrics.frag");
+pub const TEXT_SHADER_VS: &str = include_str!("../shaders/text.vert");
+pub const TEXT_SHADER_FS: &str = include_str!("../shaders/text.frag");
+pub const OUTLINE_SHADER_VS: &str = include_str!("../shaders/outline.vert");
+pub const OUTLINE_SHADER_FS: &str = include_str!("../shaders/outline.frag");
+pub const FULLSCREEN_SHADER_VS: &str = include_str!("../shaders/fullscreen.vert");
+pub const COPY_SHADER_FS: &str = include_str!("../shaders/copy.frag");
+pub const MOTION_BLUR_SHADER_FS: &str = include_str!("../shaders/motion_blur.frag");
+pub const FXAA_SHADER_FS: &str = include_str!("../shaders/fxaa.frag");
+pub const BLOOM_EXTRACT_SHADER_FS: &str = include_str!("../shaders/bloom_extract.frag");
+pub const BLUR_SHADER_FS: &str = include_str!("../shaders/blur.frag");
+pub const BLOOM_COMBINE_SHADER_FS: &str = include_str!("../shaders/bloom_combine.frag");
+pub const TONE_MAP_SHADER_FS: &str = include_str!("../shaders/tone_map.frag");
+pub const SKY_SHADER_VS: &str = include_str!("../shaders/sky.vert");
+pub const SKY_SHADER_FS: &str = include_str!("../shaders/sky.frag");
+pub const SHADOW_SHADER_VS: &str = include_str!("../shaders/shadow.vert");
+pub const SHADOW_SHADER_FS: &str = include_str!("../shaders/shadow.frag");
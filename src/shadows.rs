@@ -0,0 +1,190 @@
+use std::ptr;
+
+use cgmath::*;
+use gl::*;
+use gl::types::*;
+
+use crate::{camera::Camera, glsl::Defines, shader::{ShaderError, UniformBuffer, SHADOW_BLOCK_BINDING, SHADOW_MAP_UNIT}, shader_watcher::ShaderWatcher, shaders::*, world::World};
+
+pub const CASCADES: usize = 4; // matches the arrays in shadows.glsl
+const SHADOW_BLOCK_SIZE: usize = CASCADES * 64 + 2 * 16; // std140 `Shadows`
+const SPLIT_LAMBDA: f32 = 0.75; // 0 splits the range evenly, 1 logarithmically
+const CASTER_DISTANCE: f32 = 128.0; // how far towards the light casters outside the view still count
+
+// cascaded shadow maps from the sun (or the moon at night). each cascade covers a slice of the
+// view frustum, the near ones small so shadows close to the camera stay sharp
+pub struct ShadowMaps {
+    pub resolution: i32,
+    pub cascade_ends: [f32; CASCADES], // view space depth
+    pub chunks_drawn: usize, // summed over the cascades
+
+    light_space: [Matrix4<f32>; CASCADES],
+    texel_sizes: [f32; CASCADES],
+    fbo: u32,
+    texture: u32, // depth array, one layer per cascade
+    block: UniformBuffer,
+    shader: ShaderWatcher,
+}
+
+impl ShadowMaps {
+    pub unsafe fn new(resolution: i32) -> Result<Self, ShaderError> {
+        let mut texture = 0;
+        GenTextures(1, &mut texture);
+        BindTexture(TEXTURE_2D_ARRAY, texture);
+        TexImage3D(
+            TEXTURE_2D_ARRAY, 0, DEPTH_COMPONENT24 as GLint,
+            resolution, resolution, CASCADES as GLsizei,
+            0, DEPTH_COMPONENT, FLOAT, ptr::null(),
+        );
+        TexParameteri(TEXTURE_2D_ARRAY, TEXTURE_MIN_FILTER, LINEAR as GLint);
+        TexParameteri(TEXTURE_2D_ARRAY, TEXTURE_MAG_FILTER, LINEAR as GLint);
+        TexParameteri(TEXTURE_2D_ARRAY, TEXTURE_WRAP_S, CLAMP_TO_BORDER as GLint);
+        TexParameteri(TEXTURE_2D_ARRAY, TEXTURE_WRAP_T, CLAMP_TO_BORDER as GLint);
+        TexParameterfv(TEXTURE_2D_ARRAY, TEXTURE_BORDER_COLOR, [1.0f32; 4].as_ptr()); // lit outside the map
+        // hardware depth comparison, linear filtering then gives 2x2 pcf per tap for free
+        TexParameteri(TEXTURE_2D_ARRAY, TEXTURE_COMPARE_MODE, COMPARE_REF_TO_TEXTURE as GLint);
+        TexParameteri(TEXTURE_2D_ARRAY, TEXTURE_COMPARE_FUNC, LEQUAL as GLint);
+        BindTexture(TEXTURE_2D_ARRAY, 0);
+
+        let mut fbo = 0;
+        GenFramebuffers(1, &mut fbo);
+        BindFramebuffer(FRAMEBUFFER, fbo);
+        FramebufferTextureLayer(FRAMEBUFFER, DEPTH_ATTACHMENT, texture, 0, 0);
+        DrawBuffer(NONE);
+        ReadBuffer(NONE);
+        if CheckFramebufferStatus(FRAMEBUFFER) != FRAMEBUFFER_COMPLETE {
+            println!("ERROR::FRAMEBUFFER:: shadow map {resolution}x{resolution} is not complete!");
+        }
+        BindFramebuffer(FRAMEBUFFER, 0);
+
+        Ok(Self {
+            resolution,
+            cascade_ends: [0.0; CASCADES],
+            chunks_drawn: 0,
+
+            light_space: [Matrix4::identity(); CASCADES],
+            texel_sizes: [0.0; CASCADES],
+            fbo,
+            texture,
+            block: UniformBuffer::new(SHADOW_BLOCK_BINDING, SHADOW_BLOCK_SIZE),
+            shader: ShaderWatcher::new("shadow", SHADOW_SHADER_VS, SHADOW_SHADER_FS, Defines::new())?,
+        })
+    }
+
+    pub fn shaders(&mut self) -> [&mut ShaderWatcher; 1] {
+        [&mut self.shader]
+    }
+
+    // where each cascade stops, between the camera's near plane and `far`. a mix of an even and
+    // a logarithmic split, the log one alone makes the last cascades huge
+    pub fn split(near: f32, far: f32) -> [f32; CASCADES] {
+        Self::split_with(near, far, SPLIT_LAMBDA)
+    }
+
+    fn split_with(near: f32, far: f32, lambda: f32) -> [f32; CASCADES] {
+        let mut ends = [0.0; CASCADES];
+        for (i, end) in ends.iter_mut().enumerate() {
+            let p = (i + 1) as f32 / CASCADES as f32;
+            let log = near * (far / near).powf(p);
+            let even = near + (far - near) * p;
+            *end = even + (log - even) * lambda;
+        }
+
+        ends
+    }
+
+    // an orthographic box around the frustum slice, as seen from the light. it's fit around the
+    // slice's bounding sphere and snapped to whole texels so shadow edges don't crawl as the camera
+    // turns and moves. returns the matrix, the sphere and the world size of a texel
+    fn fit(&self, corners: &[Vector3<f32>; 8], light_direction: Vector3<f32>) -> (Matrix4<f32>, Vector3<f32>, f32, f32) {
+        let center = corners.iter().fold(Vector3::zero(), |sum, c| sum + c) / 8.0;
+        let radius = corners.iter().map(|c| c.distance(center)).fold(0.0, f32::max).ceil();
+
+        let up = if light_direction.y.abs() > 0.99 { Vector3::unit_z() } else { Vector3::unit_y() };
+        let light_view = Matrix4::look_at_rh(Point3::origin(), Point3::from_vec(-light_direction), up);
+
+        let texel = radius * 2.0 / self.resolution as f32;
+        let eye = light_view.transform_point(Point3::from_vec(center));
+        let (x, y) = ((eye.x / texel).floor() * texel, (eye.y / texel).floor() * texel);
+
+        // looking down -z, so the distances to the near and far planes are -z
+        let proj = ortho(x - radius, x + radius, y - radius, y + radius, -eye.z - radius - CASTER_DISTANCE, -eye.z + radius);
+
+        (proj * light_view, center, radius, texel)
+    }
+
+    // renders every cascade and leaves the map bound to `SHADOW_MAP_UNIT`. runs before the scene,
+    // it binds its own framebuffer and viewport. nothing past `max_distance` gets a shadow.
+    // the meshes are last frame's, `World::draw` is what builds them
+    pub unsafe fn render(&mut self, camera: &Camera, light_direction: Vector3<f32>, max_distance: f32, world: &World) {
+        let light_direction = light_direction.normalize();
+        let far = camera.far.min(max_distance).max(camera.near * 2.0);
+        self.cascade_ends = Self::split(camera.near, far);
+
+        BindFramebuffer(FRAMEBUFFER, self.fbo);
+        Viewport(0, 0, self.resolution, self.resolution);
+        Enable(DEPTH_TEST);
+        Enable(POLYGON_OFFSET_FILL);
+        PolygonOffset(2.0, 4.0);
+        self.shader.shader.use_shader();
+        self.chunks_drawn = 0;
+
+        let mut start = camera.near;
+        for i in 0..CASCADES {
+            let corners = camera.frustum_corners(start, self.cascade_ends[i]);
+            let (light_space, center, radius, texel) = self.fit(&corners, light_direction);
+            self.light_space[i] = light_space;
+            self.texel_sizes[i] = texel;
+            start = self.cascade_ends[i];
+
+            FramebufferTextureLayer(FRAMEBUFFER, DEPTH_ATTACHMENT, self.texture, 0, i as GLint);
+            Clear(DEPTH_BUFFER_BIT);
+            self.shader.shader.set_uniform("lightSpace", light_space);
+            self.chunks_drawn += world.draw_depth(&self.shader.shader, center, radius, light_direction);
+        }
+
+        Disable(POLYGON_OFFSET_FILL);
+        BindFramebuffer(FRAMEBUFFER, 0);
+
+        self.block.write(0, &self.light_space);
+        self.block.write(CASCADES * 64, &self.cascade_ends);
+        self.block.write(CASCADES * 64 + 16, &self.texel_sizes);
+
+        ActiveTexture(TEXTURE0 + SHADOW_MAP_UNIT);
+        BindTexture(TEXTURE_2D_ARRAY, self.texture);
+        ActiveTexture(TEXTURE0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f32; CASCADES], b: [f32; CASCADES]) {
+        for (a, b) in a.iter().zip(b) {
+            assert!((a - b).abs() < 1e-3 * b, "{a} != {b}");
+        }
+    }
+
+    #[test]
+    fn splits_increase_up_to_far() {
+        for (near, far) in [(0.1, 48.0), (0.1, 10000.0), (1.0, 2.0)] {
+            let ends = ShadowMaps::split(near, far);
+            assert!(ends[0] > near);
+            assert!(ends.windows(2).all(|pair| pair[0] < pair[1]), "{ends:?}");
+            assert_eq!(ends[CASCADES - 1], far);
+        }
+    }
+
+    #[test]
+    fn lambda_blends_even_and_log() {
+        assert_close(ShadowMaps::split_with(1.0, 101.0, 0.0), [26.0, 51.0, 76.0, 101.0]);
+        assert_close(ShadowMaps::split_with(1.0, 10000.0, 1.0), [10.0, 100.0, 1000.0, 10000.0]);
+
+        // in between stays in between
+        let (even, log) = (ShadowMaps::split_with(1.0, 10000.0, 0.0), ShadowMaps::split_with(1.0, 10000.0, 1.0));
+        for ((end, even), log) in ShadowMaps::split(1.0, 10000.0).into_iter().zip(even).zip(log) {
+            assert!(log <= end && end <= even);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use cgmath::{vec3, InnerSpace, Vector3};
+use gl::*;
+
+use crate::{glsl::Defines, shader::{ShaderError, UniformBuffer, SKY_BLOCK_BINDING}, shader_watcher::ShaderWatcher, shaders::*, time_of_day::Lighting, world::CHUNK_SIZE};
+
+const SKY_BLOCK_SIZE: usize = 8 * 16; // std140 `Sky`, eight vec4s
+const FOG_START: f32 = 0.6; // of the fog end distance
+
+// gradient sky with a sun disc, drawn behind everything. the mesh shader fogs towards the
+// same gradient through the shared `Sky` block, so the edge of the loaded world fades into it
+pub struct Sky {
+    pub zenith_color: [f32; 3],
+    pub horizon_color: [f32; 3],
+    pub sun_direction: Vector3<f32>, // towards the sun
+    pub sun_color: [f32; 3],
+    pub light_direction: Vector3<f32>, // what lights the terrain
+    pub light_color: [f32; 3],
+    pub ambient: [f32; 3],
+    pub fog_start: f32, // in blocks from the camera
+    pub fog_end: f32,
+
+    block: UniformBuffer,
+    shader: ShaderWatcher,
+    vao: u32,
+}
+
+impl Sky {
+    pub unsafe fn new() -> Result<Self, ShaderError> {
+        let mut vao = 0;
+        GenVertexArrays(1, &mut vao);
+
+        let mut sky = Self {
+            zenith_color: [0.25, 0.45, 0.85],
+            horizon_color: [0.7, 0.8, 0.95],
+            sun_direction: vec3(0.3, 0.6, 0.4).normalize(),
+            sun_color: [1.0, 0.95, 0.8],
+            light_direction: vec3(0.3, 0.6, 0.4).normalize(),
+            light_color: [1.0, 0.95, 0.85],
+            ambient: [0.45; 3],
+            fog_start: 0.0,
+            fog_end: 0.0,
+
+            block: UniformBuffer::new(SKY_BLOCK_BINDING, SKY_BLOCK_SIZE),
+            shader: ShaderWatcher::new("sky", SKY_SHADER_VS, SKY_SHADER_FS, Defines::new())?,
+            vao,
+        };
+        sky.set_render_distance(2.0);
+
+        Ok(sky)
+    }
+
+    // fog is opaque where unloaded chunks may start, `render_distance` in chunks like `World`.
+    // chunks load by distance from the camera's chunk, and the camera can be anywhere inside it,
+    // so only one chunk less than that is loaded in every direction
+    pub fn set_render_distance(&mut self, render_distance: f32) {
+        self.fog_end = (render_distance - 1.0).max(0.5) * CHUNK_SIZE as f32;
+        self.fog_start = self.fog_end * FOG_START;
+    }
+
+    pub fn shaders(&mut self) -> [&mut ShaderWatcher; 1] {
+        [&mut self.shader]
+    }
+
+    pub fn apply(&mut self, lighting: &Lighting) {
+        self.zenith_color = lighting.zenith_color;
+        self.horizon_color = lighting.horizon_color;
+        self.sun_direction = lighting.sun_direction;
+        self.sun_color = lighting.sun_color;
+        self.light_direction = lighting.light_direction;
+        self.light_color = lighting.light_color;
+        self.ambient = lighting.ambient;
+    }
+
+    // once per frame, before anything reads the `Sky` block
+    pub unsafe fn upload(&self) {
+        let [zr, zg, zb] = self.zenith_color;
+        let [hr, hg, hb] = self.horizon_color;
+        let [sr, sg, sb] = self.sun_color;
+        let [lr, lg, lb] = self.light_color;
+        let [ar, ag, ab] = self.ambient;
+        let sun = self.sun_direction.normalize();
+        let light = self.light_direction.normalize();
+
+        let data: [[f32; 4]; 8] = [
+            [zr, zg, zb, 1.0],
+            [hr, hg, hb, 1.0],
+            [sun.x, sun.y, sun.z, 0.0],
+            [sr, sg, sb, 1.0],
+            [self.fog_start, self.fog_end, 0.0, 0.0],
+            [light.x, light.y, light.z, 0.0],
+            [lr, lg, lb, 1.0],
+            [ar, ag, ab, 1.0],
+        ];
+        self.block.write(0, &data);
+    }
+
+    // fills the whole screen, so it replaces clearing the color buffer
+    pub unsafe fn draw(&self) {
+        Disable(DEPTH_TEST);
+        DepthMask(FALSE);
+
+        self.shader.shader.use_shader();
+        BindVertexArray(self.vao);
+        DrawArrays(TRIANGLES, 0, 3);
+        BindVertexArray(0);
+
+        DepthMask(TRUE);
+        Enable(DEPTH_TEST);
+    }
+}
@@ -0,0 +1,223 @@
+use std::path::Path;
+
+use cgmath::Vector3;
+use noise::{core::perlin::perlin_4d, permutationtable::PermutationTable, Vector4 as NVec4};
+
+use crate::world::{Voxel, CHUNK_SIZE};
+
+// anything that can fill a chunk's voxels given the chunk position (in chunk units). `biomes` has
+// one index into `biomes::BIOMES` per column, x * CHUNK_SIZE + z, and comes in as plains
+pub trait TerrainGenerator: Send + Sync {
+    fn generate(&self, chunk_pos: Vector3<f32>, voxels: &mut [Voxel], biomes: &mut [u8]);
+}
+
+pub fn voxel_index(x: usize, y: usize, z: usize) -> usize {
+    x * (CHUNK_SIZE * CHUNK_SIZE) + y * CHUNK_SIZE + z
+}
+
+// the original 4d perlin blob field
+pub struct PerlinGenerator {
+    hasher: PermutationTable,
+}
+
+impl PerlinGenerator {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            hasher: PermutationTable::new(seed),
+        }
+    }
+}
+
+impl TerrainGenerator for PerlinGenerator {
+    fn generate(&self, chunk_pos: Vector3<f32>, voxels: &mut [Voxel], _biomes: &mut [u8]) {
+        let upd_pos = chunk_pos * CHUNK_SIZE as f32 * 0.5;
+
+        for (i, voxel) in voxels.iter_mut().enumerate() {
+            let x = i / (CHUNK_SIZE * CHUNK_SIZE);
+            let y = (i % (CHUNK_SIZE * CHUNK_SIZE)) / CHUNK_SIZE;
+            let z = i % CHUNK_SIZE;
+
+            let perlin = perlin_4d(
+                NVec4::new(
+                    (x as f64 / 14.0) + upd_pos.x as f64,
+                    (y as f64 / 14.0) + upd_pos.y as f64,
+                    (z as f64 / 14.0) + upd_pos.z as f64,
+                    1.0, // rand_betw(0.0, 0.5)
+                ), &self.hasher) * 32.0;
+            if 10 < perlin as usize {
+                *voxel = Voxel::ground();
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeMode {
+    Tile,  // the image repeats forever
+    Clamp, // the edge pixels stretch outwards
+}
+
+// builds columns of ground from a grayscale image, black = min_height, white = max_height.
+// one pixel covers `horizontal_scale` voxels and pixel (0, 0) sits at world x = 0, z = 0
+pub struct HeightmapGenerator {
+    width: usize,
+    depth: usize,
+    heights: Vec<f32>, // normalized 0..1, row major (z * width + x)
+
+    pub min_height: f32,
+    pub max_height: f32,
+    pub horizontal_scale: f32,
+    pub edge_mode: EdgeMode,
+}
+
+impl HeightmapGenerator {
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        min_height: f32,
+        max_height: f32,
+        edge_mode: EdgeMode,
+    ) -> Result<Self, image::ImageError> {
+        let image = image::open(path)?.into_luma16();
+        let (width, depth) = image.dimensions();
+        let heights = image.into_raw()
+            .into_iter()
+            .map(|h| h as f32 / u16::MAX as f32)
+            .collect();
+
+        Ok(Self::from_heights(width as usize, depth as usize, heights, min_height, max_height, edge_mode))
+    }
+
+    pub fn from_heights(
+        width: usize,
+        depth: usize,
+        heights: Vec<f32>,
+        min_height: f32,
+        max_height: f32,
+        edge_mode: EdgeMode,
+    ) -> Self {
+        assert!(width > 0 && depth > 0, "heightmap must not be empty");
+        assert_eq!(heights.len(), width * depth, "heightmap size does not match its dimensions");
+
+        Self {
+            width,
+            depth,
+            heights,
+            min_height,
+            max_height,
+            horizontal_scale: 1.0,
+            edge_mode,
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.depth)
+    }
+
+    fn pixel(&self, x: i64, z: i64) -> f32 {
+        let (x, z) = match self.edge_mode {
+            EdgeMode::Tile => (
+                x.rem_euclid(self.width as i64),
+                z.rem_euclid(self.depth as i64),
+            ),
+            EdgeMode::Clamp => (
+                x.clamp(0, self.width as i64 - 1),
+                z.clamp(0, self.depth as i64 - 1),
+            ),
+        };
+
+        self.heights[z as usize * self.width + x as usize]
+    }
+
+    // bilinear so that scaled up maps don't turn into staircases
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let px = x / self.horizontal_scale;
+        let pz = z / self.horizontal_scale;
+
+        let x0 = px.floor();
+        let z0 = pz.floor();
+        let tx = px - x0;
+        let tz = pz - z0;
+        let (x0, z0) = (x0 as i64, z0 as i64);
+
+        let top = self.pixel(x0, z0) * (1.0 - tx) + self.pixel(x0 + 1, z0) * tx;
+        let bottom = self.pixel(x0, z0 + 1) * (1.0 - tx) + self.pixel(x0 + 1, z0 + 1) * tx;
+        let h = top * (1.0 - tz) + bottom * tz;
+
+        self.min_height + h * (self.max_height - self.min_height)
+    }
+}
+
+impl TerrainGenerator for HeightmapGenerator {
+    fn generate(&self, chunk_pos: Vector3<f32>, voxels: &mut [Voxel], _biomes: &mut [u8]) {
+        let origin = chunk_pos * CHUNK_SIZE as f32;
+
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let height = self.height_at(origin.x + x as f32 + 0.5, origin.z + z as f32 + 0.5);
+
+                for y in 0..CHUNK_SIZE {
+                    if origin.y + (y as f32) < height {
+                        voxels[voxel_index(x, y, z)] = Voxel::ground();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2x2, one pixel per voxel, heights 0..10
+    fn heightmap(edge_mode: EdgeMode) -> HeightmapGenerator {
+        HeightmapGenerator::from_heights(2, 2, vec![0.0, 0.2, 0.6, 1.0], 0.0, 10.0, edge_mode)
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn corners_are_the_pixel_values() {
+        let map = heightmap(EdgeMode::Clamp);
+        assert_close(map.height_at(0.0, 0.0), 0.0);
+        assert_close(map.height_at(1.0, 0.0), 2.0);
+        assert_close(map.height_at(0.0, 1.0), 6.0);
+        assert_close(map.height_at(1.0, 1.0), 10.0);
+    }
+
+    #[test]
+    fn midpoint_is_bilinear() {
+        let map = heightmap(EdgeMode::Clamp);
+        assert_close(map.height_at(0.5, 0.0), 1.0);
+        assert_close(map.height_at(0.5, 0.5), 4.5);
+    }
+
+    #[test]
+    fn tile_wraps_around() {
+        let map = heightmap(EdgeMode::Tile);
+        assert_close(map.height_at(2.0, 0.0), map.height_at(0.0, 0.0));
+        assert_close(map.height_at(-1.0, 1.0), map.height_at(1.0, 1.0));
+        assert_close(map.height_at(3.0, -2.0), map.height_at(1.0, 0.0));
+        // halfway between the last pixel and the first one again
+        assert_close(map.height_at(1.5, 0.0), 1.0);
+    }
+
+    #[test]
+    fn clamp_repeats_the_edge() {
+        let map = heightmap(EdgeMode::Clamp);
+        assert_close(map.height_at(-5.0, -5.0), 0.0);
+        assert_close(map.height_at(7.0, 0.0), 2.0);
+        assert_close(map.height_at(7.0, 9.0), 10.0);
+        assert_close(map.height_at(-3.0, 1.0), 6.0);
+    }
+
+    #[test]
+    fn horizontal_scale_stretches_pixels() {
+        let mut map = heightmap(EdgeMode::Clamp);
+        map.horizontal_scale = 4.0;
+        assert_close(map.height_at(4.0, 0.0), 2.0);
+        assert_close(map.height_at(2.0, 0.0), 1.0);
+    }
+}
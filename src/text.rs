@@ -0,0 +1,325 @@
+use std::{ffi::c_void, mem::{offset_of, size_of, size_of_val}};
+
+use gl::*;
+use gl::types::*;
+
+use crate::{glsl::Defines, shader::ShaderError, shader_watcher::ShaderWatcher, shaders::*};
+
+const GLYPH_SIZE: usize = 8;
+const ATLAS_COLUMNS: usize = 16;
+const ATLAS_ROWS: usize = 6; // 95 printable ascii glyphs, 32..=126
+const FIRST_GLYPH: u8 = b' ';
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TextVertex {
+    position: [f32; 2], // pixels from the top left of the window
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+// queues strings as textured quads against an embedded 8x8 bitmap font
+// and draws everything queued in a single call
+pub struct TextRenderer {
+    shader: ShaderWatcher,
+    atlas: u32,
+    vao: u32,
+    vbo: u32,
+    vertices: Vec<TextVertex>,
+}
+
+impl TextRenderer {
+    pub unsafe fn new() -> Result<Self, ShaderError> {
+        let shader = ShaderWatcher::new("text", TEXT_SHADER_VS, TEXT_SHADER_FS, Defines::new())?;
+
+        // unpack the 1 bit glyphs into an r8 atlas
+        let (atlas_w, atlas_h) = (ATLAS_COLUMNS * GLYPH_SIZE, ATLAS_ROWS * GLYPH_SIZE);
+        let mut pixels = vec![0u8; atlas_w * atlas_h];
+        for (i, glyph) in FONT.iter().enumerate() {
+            let (gx, gy) = ((i % ATLAS_COLUMNS) * GLYPH_SIZE, (i / ATLAS_COLUMNS) * GLYPH_SIZE);
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..GLYPH_SIZE {
+                    if bits >> col & 1 == 1 {
+                        pixels[(gy + row) * atlas_w + gx + col] = 255;
+                    }
+                }
+            }
+        }
+
+        let mut atlas = 0;
+        GenTextures(1, &mut atlas);
+        BindTexture(TEXTURE_2D, atlas);
+        PixelStorei(UNPACK_ALIGNMENT, 1);
+        TexImage2D(
+            TEXTURE_2D,
+            0,
+            R8 as GLint,
+            atlas_w as GLsizei,
+            atlas_h as GLsizei,
+            0,
+            RED,
+            UNSIGNED_BYTE,
+            pixels.as_ptr() as *const c_void,
+        );
+        PixelStorei(UNPACK_ALIGNMENT, 4);
+        TexParameteri(TEXTURE_2D, TEXTURE_MIN_FILTER, NEAREST as GLint);
+        TexParameteri(TEXTURE_2D, TEXTURE_MAG_FILTER, NEAREST as GLint);
+        TexParameteri(TEXTURE_2D, TEXTURE_WRAP_S, CLAMP_TO_EDGE as GLint);
+        TexParameteri(TEXTURE_2D, TEXTURE_WRAP_T, CLAMP_TO_EDGE as GLint);
+
+        let mut vao = 0;
+        let mut vbo = 0;
+        GenVertexArrays(1, &mut vao);
+        GenBuffers(1, &mut vbo);
+        BindVertexArray(vao);
+        BindBuffer(ARRAY_BUFFER, vbo);
+
+        let stride = size_of::<TextVertex>() as GLsizei;
+        EnableVertexAttribArray(0);
+        VertexAttribPointer(0, 2, FLOAT, FALSE, stride, offset_of!(TextVertex, position) as *const c_void);
+        EnableVertexAttribArray(1);
+        VertexAttribPointer(1, 2, FLOAT, FALSE, stride, offset_of!(TextVertex, uv) as *const c_void);
+        EnableVertexAttribArray(2);
+        VertexAttribPointer(2, 4, FLOAT, FALSE, stride, offset_of!(TextVertex, color) as *const c_void);
+        BindVertexArray(0);
+
+        Ok(Self {
+            shader,
+            atlas,
+            vao,
+            vbo,
+            vertices: Vec::new(),
+        })
+    }
+
+    pub fn shaders(&mut self) -> [&mut ShaderWatcher; 1] {
+        [&mut self.shader]
+    }
+
+    // size in pixels of `text` drawn at `scale` (1.0 = 8 pixel glyphs)
+    pub fn measure(text: &str, scale: f32) -> (f32, f32) {
+        let advance = GLYPH_SIZE as f32 * scale;
+        let lines = text.lines().count().max(1);
+        let widest = text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+
+        (widest as f32 * advance, lines as f32 * advance)
+    }
+
+    // the atlas glyph and top left corner of every character that draws something, spaces don't
+    fn layout(text: &str, x: f32, y: f32, scale: f32) -> impl Iterator<Item = (usize, [f32; 2])> + '_ {
+        let advance = GLYPH_SIZE as f32 * scale;
+
+        text.lines().enumerate().flat_map(move |(line_idx, line)| {
+            let y0 = y + line_idx as f32 * advance;
+
+            line.chars().enumerate().filter_map(move |(i, c)| {
+                let glyph = match c {
+                    ' '..='~' => c as u8 - FIRST_GLYPH,
+                    _ => b'?' - FIRST_GLYPH,
+                } as usize;
+                (glyph != 0).then_some((glyph, [x + i as f32 * advance, y0]))
+            })
+        })
+    }
+
+    pub fn queue(&mut self, text: &str, x: f32, y: f32, scale: f32, color: [f32; 4]) {
+        let advance = GLYPH_SIZE as f32 * scale;
+        let (u_step, v_step) = (1.0 / ATLAS_COLUMNS as f32, 1.0 / ATLAS_ROWS as f32);
+
+        for (glyph, [x0, y0]) in Self::layout(text, x, y, scale) {
+            let (x1, y1) = (x0 + advance, y0 + advance);
+            let u0 = (glyph % ATLAS_COLUMNS) as f32 * u_step;
+            let v0 = (glyph / ATLAS_COLUMNS) as f32 * v_step;
+            let (u1, v1) = (u0 + u_step, v0 + v_step);
+
+            let quad = [
+                ([x0, y0], [u0, v0]),
+                ([x1, y0], [u1, v0]),
+                ([x1, y1], [u1, v1]),
+                ([x0, y0], [u0, v0]),
+                ([x1, y1], [u1, v1]),
+                ([x0, y1], [u0, v1]),
+            ];
+            self.vertices.extend(quad.iter().map(|(position, uv)| TextVertex {
+                position: *position,
+                uv: *uv,
+                color,
+            }));
+        }
+    }
+
+    // same as `queue` with a dark copy one pixel behind, readable over bright terrain
+    pub fn queue_shadowed(&mut self, text: &str, x: f32, y: f32, scale: f32, color: [f32; 4]) {
+        self.queue(text, x + scale, y + scale, scale, [0.0, 0.0, 0.0, color[3] * 0.8]);
+        self.queue(text, x, y, scale, color);
+    }
+
+    pub unsafe fn flush(&mut self, screen_width: i32, screen_height: i32) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        Disable(DEPTH_TEST);
+        Enable(BLEND);
+        BlendFunc(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+
+        self.shader.shader.use_shader();
+        self.shader.shader.set_uniform("screenWidth", screen_width as f32);
+        self.shader.shader.set_uniform("screenHeight", screen_height as f32);
+        self.shader.shader.set_uniform("atlas", 0);
+        ActiveTexture(TEXTURE0);
+        BindTexture(TEXTURE_2D, self.atlas);
+
+        BindVertexArray(self.vao);
+        BindBuffer(ARRAY_BUFFER, self.vbo);
+        BufferData(
+            ARRAY_BUFFER,
+            size_of_val(self.vertices.as_slice()) as GLsizeiptr,
+            self.vertices.as_ptr() as *const c_void,
+            STREAM_DRAW,
+        );
+        DrawArrays(TRIANGLES, 0, self.vertices.len() as GLsizei);
+        BindVertexArray(0);
+
+        Disable(BLEND);
+        Enable(DEPTH_TEST);
+        self.vertices.clear();
+    }
+}
+
+// public domain 8x8 font (font8x8_basic), one byte per row, least significant bit on the left
+const FONT: [[u8; 8]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x18, 0x3C, 0x3C, 0x18, 0x18, 0x00, 0x18, 0x00], // !
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // "
+    [0x36, 0x36, 0x7F, 0x36, 0x7F, 0x36, 0x36, 0x00], // #
+    [0x0C, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x0C, 0x00], // $
+    [0x00, 0x63, 0x33, 0x18, 0x0C, 0x66, 0x63, 0x00], // %
+    [0x1C, 0x36, 0x1C, 0x6E, 0x3B, 0x33, 0x6E, 0x00], // &
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // '
+    [0x18, 0x0C, 0x06, 0x06, 0x06, 0x0C, 0x18, 0x00], // (
+    [0x06, 0x0C, 0x18, 0x18, 0x18, 0x0C, 0x06, 0x00], // )
+    [0x00, 0x66, 0x3C, 0xFF, 0x3C, 0x66, 0x00, 0x00], // *
+    [0x00, 0x0C, 0x0C, 0x3F, 0x0C, 0x0C, 0x00, 0x00], // +
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ,
+    [0x00, 0x00, 0x00, 0x3F, 0x00, 0x00, 0x00, 0x00], // -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x00], // .
+    [0x60, 0x30, 0x18, 0x0C, 0x06, 0x03, 0x01, 0x00], // /
+    [0x3E, 0x63, 0x73, 0x7B, 0x6F, 0x67, 0x3E, 0x00], // 0
+    [0x0C, 0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x3F, 0x00], // 1
+    [0x1E, 0x33, 0x30, 0x1C, 0x06, 0x33, 0x3F, 0x00], // 2
+    [0x1E, 0x33, 0x30, 0x1C, 0x30, 0x33, 0x1E, 0x00], // 3
+    [0x38, 0x3C, 0x36, 0x33, 0x7F, 0x30, 0x78, 0x00], // 4
+    [0x3F, 0x03, 0x1F, 0x30, 0x30, 0x33, 0x1E, 0x00], // 5
+    [0x1C, 0x06, 0x03, 0x1F, 0x33, 0x33, 0x1E, 0x00], // 6
+    [0x3F, 0x33, 0x30, 0x18, 0x0C, 0x0C, 0x0C, 0x00], // 7
+    [0x1E, 0x33, 0x33, 0x1E, 0x33, 0x33, 0x1E, 0x00], // 8
+    [0x1E, 0x33, 0x33, 0x3E, 0x30, 0x18, 0x0E, 0x00], // 9
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x00], // :
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ;
+    [0x18, 0x0C, 0x06, 0x03, 0x06, 0x0C, 0x18, 0x00], // <
+    [0x00, 0x00, 0x3F, 0x00, 0x00, 0x3F, 0x00, 0x00], // =
+    [0x06, 0x0C, 0x18, 0x30, 0x18, 0x0C, 0x06, 0x00], // >
+    [0x1E, 0x33, 0x30, 0x18, 0x0C, 0x00, 0x0C, 0x00], // ?
+    [0x3E, 0x63, 0x7B, 0x7B, 0x7B, 0x03, 0x1E, 0x00], // @
+    [0x0C, 0x1E, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x00], // A
+    [0x3F, 0x66, 0x66, 0x3E, 0x66, 0x66, 0x3F, 0x00], // B
+    [0x3C, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3C, 0x00], // C
+    [0x1F, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1F, 0x00], // D
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x46, 0x7F, 0x00], // E
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x06, 0x0F, 0x00], // F
+    [0x3C, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7C, 0x00], // G
+    [0x33, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x33, 0x00], // H
+    [0x1E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // I
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E, 0x00], // J
+    [0x67, 0x66, 0x36, 0x1E, 0x36, 0x66, 0x67, 0x00], // K
+    [0x0F, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7F, 0x00], // L
+    [0x63, 0x77, 0x7F, 0x7F, 0x6B, 0x63, 0x63, 0x00], // M
+    [0x63, 0x67, 0x6F, 0x7B, 0x73, 0x63, 0x63, 0x00], // N
+    [0x1C, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1C, 0x00], // O
+    [0x3F, 0x66, 0x66, 0x3E, 0x06, 0x06, 0x0F, 0x00], // P
+    [0x1E, 0x33, 0x33, 0x33, 0x3B, 0x1E, 0x38, 0x00], // Q
+    [0x3F, 0x66, 0x66, 0x3E, 0x36, 0x66, 0x67, 0x00], // R
+    [0x1E, 0x33, 0x07, 0x0E, 0x38, 0x33, 0x1E, 0x00], // S
+    [0x3F, 0x2D, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // T
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3F, 0x00], // U
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // V
+    [0x63, 0x63, 0x63, 0x6B, 0x7F, 0x77, 0x63, 0x00], // W
+    [0x63, 0x63, 0x36, 0x1C, 0x1C, 0x36, 0x63, 0x00], // X
+    [0x33, 0x33, 0x33, 0x1E, 0x0C, 0x0C, 0x1E, 0x00], // Y
+    [0x7F, 0x63, 0x31, 0x18, 0x4C, 0x66, 0x7F, 0x00], // Z
+    [0x1E, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1E, 0x00], // [
+    [0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x40, 0x00], // \
+    [0x1E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1E, 0x00], // ]
+    [0x08, 0x1C, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF], // _
+    [0x0C, 0x0C, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // `
+    [0x00, 0x00, 0x1E, 0x30, 0x3E, 0x33, 0x6E, 0x00], // a
+    [0x07, 0x06, 0x06, 0x3E, 0x66, 0x66, 0x3B, 0x00], // b
+    [0x00, 0x00, 0x1E, 0x33, 0x03, 0x33, 0x1E, 0x00], // c
+    [0x38, 0x30, 0x30, 0x3E, 0x33, 0x33, 0x6E, 0x00], // d
+    [0x00, 0x00, 0x1E, 0x33, 0x3F, 0x03, 0x1E, 0x00], // e
+    [0x1C, 0x36, 0x06, 0x0F, 0x06, 0x06, 0x0F, 0x00], // f
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x1F], // g
+    [0x07, 0x06, 0x36, 0x6E, 0x66, 0x66, 0x67, 0x00], // h
+    [0x0C, 0x00, 0x0E, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // i
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E], // j
+    [0x07, 0x06, 0x66, 0x36, 0x1E, 0x36, 0x67, 0x00], // k
+    [0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // l
+    [0x00, 0x00, 0x33, 0x7F, 0x7F, 0x6B, 0x63, 0x00], // m
+    [0x00, 0x00, 0x1F, 0x33, 0x33, 0x33, 0x33, 0x00], // n
+    [0x00, 0x00, 0x1E, 0x33, 0x33, 0x33, 0x1E, 0x00], // o
+    [0x00, 0x00, 0x3B, 0x66, 0x66, 0x3E, 0x06, 0x0F], // p
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x78], // q
+    [0x00, 0x00, 0x3B, 0x6E, 0x66, 0x06, 0x0F, 0x00], // r
+    [0x00, 0x00, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x00], // s
+    [0x08, 0x0C, 0x3E, 0x0C, 0x0C, 0x2C, 0x18, 0x00], // t
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6E, 0x00], // u
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // v
+    [0x00, 0x00, 0x63, 0x6B, 0x7F, 0x7F, 0x36, 0x00], // w
+    [0x00, 0x00, 0x63, 0x36, 0x1C, 0x36, 0x63, 0x00], // x
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3E, 0x30, 0x1F], // y
+    [0x00, 0x00, 0x3F, 0x19, 0x0C, 0x26, 0x3F, 0x00], // z
+    [0x38, 0x0C, 0x0C, 0x07, 0x0C, 0x0C, 0x38, 0x00], // {
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // |
+    [0x07, 0x0C, 0x0C, 0x38, 0x0C, 0x0C, 0x07, 0x00], // }
+    [0x6E, 0x3B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ~
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(c: char) -> usize {
+        (c as u8 - FIRST_GLYPH) as usize
+    }
+
+    #[test]
+    fn measures_the_widest_line() {
+        assert_eq!(TextRenderer::measure("ab\ncde", 2.0), (48.0, 32.0));
+        assert_eq!(TextRenderer::measure("abc\n\nd", 1.0), (24.0, 24.0));
+        // a trailing newline doesn't start another line
+        assert_eq!(TextRenderer::measure("ab\n", 1.0), (16.0, 8.0));
+        assert_eq!(TextRenderer::measure("", 1.0), (0.0, 8.0));
+    }
+
+    #[test]
+    fn lays_out_lines_below_each_other() {
+        let glyphs: Vec<_> = TextRenderer::layout("a b\ncd\n\ne\u{e9}", 10.0, 20.0, 2.0).collect();
+        assert_eq!(glyphs, [
+            (glyph('a'), [10.0, 20.0]),
+            (glyph('b'), [42.0, 20.0]), // past the space, which draws nothing
+            (glyph('c'), [10.0, 36.0]),
+            (glyph('d'), [26.0, 36.0]),
+            (glyph('e'), [10.0, 68.0]), // the empty line still takes its space
+            (glyph('?'), [26.0, 68.0]), // outside the font
+        ]);
+
+        // everything stays inside what `measure` says
+        let text = "looking at: id 1\nxyz: -1.50 / 5.00";
+        let (w, h) = TextRenderer::measure(text, 2.0);
+        for (_, [x, y]) in TextRenderer::layout(text, 0.0, 0.0, 2.0) {
+            assert!(x + 16.0 <= w && y + 16.0 <= h);
+        }
+    }
+}
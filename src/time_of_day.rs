@@ -0,0 +1,252 @@
+use std::f32::consts::TAU;
+
+use cgmath::{vec3, InnerSpace, Vector3};
+
+use crate::util::smoothstep;
+
+const SUNRISE: f32 = 0.25; // of a day, 06:00
+const SUN_TILT: f32 = 0.35; // so the sun doesn't pass straight overhead
+
+// what the sky and the mesh shader need from the time of day
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lighting {
+    pub sun_direction: Vector3<f32>, // towards the sun, below the horizon at night
+    pub light_direction: Vector3<f32>, // the sun by day, the moon by night
+    pub light_color: [f32; 3], // skylight, already scaled by its intensity
+    pub ambient: [f32; 3],
+    pub zenith_color: [f32; 3],
+    pub horizon_color: [f32; 3],
+    pub sun_color: [f32; 3],
+}
+
+struct SkyKey {
+    zenith: [f32; 3],
+    horizon: [f32; 3],
+    sun: [f32; 3],
+    light: [f32; 3],
+    ambient: f32,
+}
+
+const NIGHT: SkyKey = SkyKey {
+    zenith: [0.01, 0.015, 0.04],
+    horizon: [0.03, 0.04, 0.08],
+    sun: [0.0, 0.0, 0.0],
+    light: [0.08, 0.1, 0.16], // moonlight
+    ambient: 0.08,
+};
+const TWILIGHT: SkyKey = SkyKey {
+    zenith: [0.2, 0.25, 0.45],
+    horizon: [0.95, 0.5, 0.25],
+    sun: [1.0, 0.55, 0.3],
+    light: [0.5, 0.3, 0.2],
+    ambient: 0.25,
+};
+const DAY: SkyKey = SkyKey {
+    zenith: [0.25, 0.45, 0.85],
+    horizon: [0.7, 0.8, 0.95],
+    sun: [1.0, 0.95, 0.8],
+    light: [1.0, 0.95, 0.85],
+    ambient: 0.45,
+};
+
+fn lerp3(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+// time of day as a fraction of a day, 0 is midnight and 0.5 is noon
+pub struct WorldClock {
+    pub time: f32,
+    pub day: u32,
+    pub day_length: f32, // real seconds per day at rate 1
+    pub rate: f32,
+    pub frozen: bool,
+}
+
+impl WorldClock {
+    pub fn new(day_length: f32, start_hour: f32) -> Self {
+        Self {
+            time: (start_hour / 24.0).rem_euclid(1.0),
+            day: 0,
+            day_length,
+            rate: 1.0,
+            frozen: false,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if self.frozen || self.day_length <= 0.0 {
+            return;
+        }
+
+        // a fast rate can pass more than one day in a frame
+        self.time += dt * self.rate / self.day_length;
+        self.day += self.time.floor() as u32;
+        self.time = self.time.fract();
+    }
+
+    pub fn set_hour(&mut self, hour: f32) {
+        self.time = (hour / 24.0).rem_euclid(1.0);
+    }
+
+    pub fn hour(&self) -> f32 {
+        self.time * 24.0
+    }
+
+    // `day 2 13:05`
+    pub fn describe(&self) -> String {
+        let minutes = (self.time * 24.0 * 60.0) as u32;
+        let frozen = if self.frozen { " (frozen)" } else { "" };
+        format!("day {} {:02}:{:02}{frozen}", self.day + 1, minutes / 60, minutes % 60)
+    }
+
+    // rises in +x, sets in -x
+    pub fn sun_direction(&self) -> Vector3<f32> {
+        let angle = (self.time - SUNRISE) * TAU;
+        vec3(angle.cos(), angle.sin(), SUN_TILT).normalize()
+    }
+
+    pub fn lighting(&self) -> Lighting {
+        let sun = self.sun_direction();
+        let (from, to, t) = match sun.y {
+            h if h < 0.0 => (&NIGHT, &TWILIGHT, smoothstep(-0.25, 0.0, h)),
+            h => (&TWILIGHT, &DAY, smoothstep(0.0, 0.3, h)),
+        };
+        let ambient = from.ambient + (to.ambient - from.ambient) * t;
+        // the light swaps between sun and moon at the horizon, fade it out there so it doesn't jump
+        let [r, g, b] = lerp3(from.light, to.light, t);
+        let fade = smoothstep(0.0, 0.1, sun.y.abs());
+
+        Lighting {
+            sun_direction: sun,
+            light_direction: if sun.y >= 0.0 { sun } else { -sun },
+            light_color: [r * fade, g * fade, b * fade],
+            ambient: [ambient; 3],
+            zenith_color: lerp3(from.zenith, to.zenith, t),
+            horizon_color: lerp3(from.horizon, to.horizon, t),
+            sun_color: lerp3(from.sun, to.sun, t),
+        }
+    }
+
+    // `time` arguments from the console: `set 6:30`, `set noon`, `freeze`, `resume`, `rate 10`.
+    // returns what to print back
+    pub fn command(&mut self, args: &[&str]) -> Result<String, String> {
+        match args {
+            [] => Ok(self.describe()),
+            ["set", value] => {
+                let hour = match *value {
+                    "midnight" => 0.0,
+                    "sunrise" => 6.0,
+                    "noon" => 12.0,
+                    "sunset" => 18.0,
+                    value => parse_hour(value).ok_or_else(|| format!("`{value}` is not a time, try 6:30 or 18.5"))?,
+                };
+                self.set_hour(hour);
+                Ok(self.describe())
+            },
+            ["freeze"] => {
+                self.frozen = true;
+                Ok(self.describe())
+            },
+            ["resume"] | ["unfreeze"] => {
+                self.frozen = false;
+                Ok(self.describe())
+            },
+            ["rate", value] => {
+                let rate: f32 = value.parse().map_err(|_| format!("`{value}` is not a number"))?;
+                if !rate.is_finite() {
+                    return Err(format!("`{value}` is not a rate"));
+                }
+                if rate < 0.0 {
+                    return Err("time can't run backwards".to_string());
+                }
+                self.rate = rate;
+                Ok(format!("time runs at {rate}x"))
+            },
+            _ => Err("usage: time [set <hh:mm|noon|midnight|sunrise|sunset> | freeze | resume | rate <x>]".to_string()),
+        }
+    }
+}
+
+fn parse_hour(value: &str) -> Option<f32> {
+    let hour = match value.split_once(':') {
+        Some((h, m)) => {
+            let (h, m) = (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?);
+            if m >= 60 {
+                return None;
+            }
+            h as f32 + m as f32 / 60.0
+        },
+        None => value.parse().ok()?,
+    };
+
+    (0.0..24.0).contains(&hour).then_some(hour)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hours() {
+        let cases = [
+            ("6:30", Some(6.5)),
+            ("0:00", Some(0.0)),
+            ("23:59", Some(23.0 + 59.0 / 60.0)),
+            ("18.5", Some(18.5)),
+            ("7", Some(7.0)),
+            ("24:00", None),
+            ("24", None),
+            ("-1", None),
+            ("6:60", None),
+            ("6:-5", None),
+            ("6.5:30", None),
+            ("NaN", None),
+            ("inf", None),
+            ("noon", None),
+            ("", None),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(parse_hour(value), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn commands() {
+        let mut clock = WorldClock::new(600.0, 0.0);
+
+        assert_eq!(clock.command(&["set", "noon"]), Ok("day 1 12:00".to_string()));
+        assert_eq!(clock.command(&["set", "6:30"]), Ok("day 1 06:30".to_string()));
+        assert!(clock.command(&["set", "25:00"]).is_err());
+        assert_eq!(clock.hour(), 6.5);
+
+        assert_eq!(clock.command(&["freeze"]), Ok("day 1 06:30 (frozen)".to_string()));
+        clock.update(100.0);
+        assert_eq!(clock.hour(), 6.5);
+        assert_eq!(clock.command(&["resume"]), Ok("day 1 06:30".to_string()));
+
+        assert_eq!(clock.command(&["rate", "10"]), Ok("time runs at 10x".to_string()));
+        for rate in ["-1", "inf", "-inf", "NaN", "fast"] {
+            assert!(clock.command(&["rate", rate]).is_err(), "{rate}");
+        }
+        assert_eq!(clock.rate, 10.0);
+
+        assert!(clock.command(&["sideways"]).is_err());
+        assert!(clock.command(&["set"]).is_err());
+    }
+
+    #[test]
+    fn days_roll_over() {
+        let mut clock = WorldClock::new(100.0, 18.0);
+        clock.update(50.0);
+        assert_eq!(clock.day, 1);
+        assert!((clock.hour() - 6.0).abs() < 1e-3, "{}", clock.hour());
+
+        // several days in one step
+        clock.rate = 10.0;
+        clock.update(35.0);
+        assert_eq!(clock.day, 4);
+        assert!((clock.hour() - 18.0).abs() < 1e-3, "{}", clock.hour());
+        assert!((0.0..1.0).contains(&clock.time));
+    }
+}
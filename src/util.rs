@@ -16,6 +16,12 @@ pub fn rand_betw
     r.gen_range(n1..n2)
 }
 
+// 0 below `edge0`, 1 above `edge1`, eased in between like glsl's
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
 pub struct SecondOrderDynamics { // make it so that input x yields in a smooth, natural output y
     xp: Vector3<f32>, // previous inputs
     y: Vector3<f32>, 
@@ -1,71 +1,178 @@
-use crate::{mesh::Vertex, util::rand_betw};
+use crate::{biomes::{self, BiomeGenerator}, block, fluid, mesh::{VoxelVertex, MAX_LIGHT}, terrain::{voxel_index, TerrainGenerator}};
 
 use cgmath::Vector3;
-use noise::{core::perlin::{perlin_2d, perlin_3d, perlin_4d}, permutationtable::PermutationTable, Perlin, Vector4 as NVec4};
-use tokio::sync::watch;
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Voxel {
     id: u8,
+    state: u8, // per block extra, for fluids the level (low 3 bits) and whether it's falling (bit 3)
 }
 
+const FALLING: u8 = 1 << 3;
+
 impl Voxel {
+    pub fn new(id: u8) -> Self {
+        Voxel { id, state: 0 }
+    }
+
     pub fn air() -> Self {
-        Voxel { id: 0 }
+        Voxel::new(block::AIR)
     }
 
     pub fn ground() -> Self {
-        Voxel { id: 1 }
+        Voxel::new(block::GROUND)
+    }
+
+    // level 0 is a source, higher levels are further from one. falling fluid is fed from above
+    // and stays full height whatever its level
+    pub fn fluid(id: u8, level: u8, falling: bool) -> Self {
+        debug_assert!(level < FALLING);
+        Voxel { id, state: level | if falling { FALLING } else { 0 } }
     }
 
     pub fn id(&self) -> u8 {
         self.id
     }
+
+    pub fn level(&self) -> u8 {
+        self.state & (FALLING - 1)
+    }
+
+    pub fn is_falling(&self) -> bool {
+        self.state & FALLING != 0
+    }
+
+    pub fn is_source(&self) -> bool {
+        self.state == 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mesher {
+    NoCulling,
+    Culled,
+}
+
+impl Mesher {
+    pub const ALL: [Mesher; 2] = [Mesher::NoCulling, Mesher::Culled];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mesher::NoCulling => "no_culling",
+            Mesher::Culled => "culled",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub vertices: Vec<VoxelVertex>,
+    pub indices: Vec<u32>, // relative to `vertices`
+}
+
+// a chunk's geometry split by how it's drawn
+#[derive(Clone, Debug, Default)]
+pub struct ChunkMesh {
+    pub solid: MeshData, // opaque and alpha tested blocks, they write depth and cast shadows
+    pub translucent: MeshData, // blended blocks like water, drawn after all solid geometry
+}
+
+impl ChunkMesh {
+    fn part(&mut self, block: u8) -> &mut MeshData {
+        match block::block(block).opacity {
+            block::Opacity::Translucent => &mut self.translucent,
+            block::Opacity::Opaque | block::Opacity::Transparent => &mut self.solid,
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.solid.vertices.len() + self.translucent.vertices.len()
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.solid.indices.len() + self.translucent.indices.len()
+    }
 }
 
 #[derive(Clone)]
 pub struct Chunk {
     voxels: Vec<Voxel>,
+    biomes: Vec<u8>, // per column, x * CHUNK_SIZE + z
     pos: Vector3<f32>,
     creation_instant: std::time::Instant,
     is_mesh: bool,
 }
 
 pub const CHUNK_SIZE: usize = 24;
+pub const REACH: f32 = 5.0; // how far away blocks can be targeted
+
+// each face's outward normal and its corners as offsets from the voxel's min corner,
+// in the order -x, +x, -y, +y, -z, +z (the face index packed into `VoxelVertex`)
+type Face = ((isize, isize, isize), [(isize, isize, isize); 4]);
+const FACES: [Face; 6] = [
+    ((-1, 0, 0), [(0, 0, 0), (0, 0, 1), (0, 1, 1), (0, 1, 0)]),
+    ((1, 0, 0), [(1, 0, 0), (1, 1, 0), (1, 1, 1), (1, 0, 1)]),
+    ((0, -1, 0), [(0, 0, 0), (1, 0, 0), (1, 0, 1), (0, 0, 1)]),
+    ((0, 1, 0), [(0, 1, 0), (0, 1, 1), (1, 1, 1), (1, 1, 0)]),
+    ((0, 0, -1), [(0, 0, 0), (0, 1, 0), (1, 1, 0), (1, 0, 0)]),
+    ((0, 0, 1), [(0, 0, 1), (1, 0, 1), (1, 1, 1), (0, 1, 1)]),
+];
+
+// texture coordinates from a corner's position in the chunk, so they keep counting up across
+// quads covering several blocks. u runs to the right seen from outside the face and v down,
+// the way the pngs are stored
+fn face_uv(face: usize, [x, y, z]: [u32; 3]) -> [u32; 2] {
+    let s = CHUNK_SIZE as u32;
+    match face {
+        0 => [z, s - y],
+        1 => [s - z, s - y],
+        2 | 3 => [x, z],
+        4 => [s - x, s - y],
+        _ => [x, s - y],
+    }
+}
 
 impl Chunk {
     pub fn new(pos: Vector3<f32>) -> Self {
-        let upd_pos = pos * CHUNK_SIZE as f32 * 0.5;
+        Self::with_generator(pos, &BiomeGenerator::new(0))
+    }
 
+    pub fn with_generator(pos: Vector3<f32>, generator: &dyn TerrainGenerator) -> Self {
         let creation_instant = std::time::Instant::now();
-        let hasher = PermutationTable::new(0);
         let mut voxels = vec![Voxel::air(); CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE];
+        let mut biomes = vec![biomes::PLAINS; CHUNK_SIZE * CHUNK_SIZE];
 
-        for i in 0..CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE {
-            let x = i / (CHUNK_SIZE * CHUNK_SIZE);
-            let y = (i % (CHUNK_SIZE * CHUNK_SIZE)) / CHUNK_SIZE;
-            let z = i % CHUNK_SIZE;
-
-            let perlin = perlin_4d(
-                NVec4::new(
-                    (x as f64 / 14.0) + upd_pos.x as f64,
-                    (y as f64 / 14.0) + upd_pos.y as f64,
-                    (z as f64 / 14.0) + upd_pos.z as f64,
-                    1.0, // rand_betw(0.0, 0.5)
-                ), &hasher) * 32.0;
-            if 10 < perlin as usize {
-                voxels[i] = Voxel::ground();
-            }
-        }
+        generator.generate(pos, &mut voxels, &mut biomes);
 
         Self {
             pos,
             voxels,
+            biomes,
             creation_instant,
             is_mesh: false,
         }
     }
 
+    pub fn empty(pos: Vector3<f32>) -> Self {
+        Self {
+            pos,
+            voxels: vec![Voxel::air(); CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE],
+            biomes: vec![biomes::PLAINS; CHUNK_SIZE * CHUNK_SIZE],
+            creation_instant: std::time::Instant::now(),
+            is_mesh: false,
+        }
+    }
+
+    pub fn set_voxel(&mut self, x: usize, y: usize, z: usize, voxel: Voxel) {
+        self.voxels[voxel_index(x, y, z)] = voxel;
+        self.is_mesh = false;
+    }
+
+    pub fn voxel(&self, x: usize, y: usize, z: usize) -> &Voxel {
+        &self.voxels[voxel_index(x, y, z)]
+    }
+
+    // nothing hears about this one, `World::destroy_voxel` is the one that wakes up the neighbours
     pub fn destroy_voxel(&mut self, pos: Vector3<f32>) {
         let n_pos = pos - self.pos * CHUNK_SIZE as f32;
         let voxel_index = Chunk::get_voxel(n_pos);
@@ -82,131 +189,125 @@ impl Chunk {
         x * (CHUNK_SIZE * CHUNK_SIZE) + y * CHUNK_SIZE + z
     }
 
-    pub fn gen_mesh_data_no_culling(&self) -> (Vec<Vertex>, Vec<u32>) {
-        let mut vertices = Vec::new();
-        let mut indices = Vec::new();
-        let mut index_offset = 0;
+    // `neighbours` are the loaded chunks, for the ambient occlusion and the fluid surfaces across the border
+    pub fn gen_mesh_data(&self, mesher: Mesher, neighbours: &HashMap<Vector3<i32>, Chunk>) -> ChunkMesh {
+        match mesher {
+            Mesher::NoCulling => self.gen_mesh_data_no_culling(neighbours),
+            Mesher::Culled => self.gen_mesh_data_culled(neighbours),
+        }
+    }
 
-        for current_voxel_pos in 0..CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE {
-            let x = current_voxel_pos / (CHUNK_SIZE * CHUNK_SIZE);
-            let y = (current_voxel_pos % (CHUNK_SIZE * CHUNK_SIZE)) / CHUNK_SIZE;
-            let z = current_voxel_pos % CHUNK_SIZE;
+    // every face of every block, hidden or not. only useful as a baseline for the bench
+    pub fn gen_mesh_data_no_culling(&self, neighbours: &HashMap<Vector3<i32>, Chunk>) -> ChunkMesh {
+        let mut mesh = ChunkMesh::default();
 
+        for current_voxel_pos in 0..CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE {
             let voxel = &self.voxels[current_voxel_pos];
-
             if voxel.id == 0 { // voxel is air
                 continue;
             }
 
-            vertices.push(Vertex::new(x as f32, y as f32, z as f32));
-            vertices.push(Vertex::new(x as f32 + 1.0, y as f32, z as f32));
-            vertices.push(Vertex::new(x as f32 + 1.0, y as f32 + 1.0, z as f32));
-            vertices.push(Vertex::new(x as f32, y as f32 + 1.0, z as f32));
-            vertices.push(Vertex::new(x as f32, y as f32, z as f32 + 1.0));
-            vertices.push(Vertex::new(x as f32 + 1.0, y as f32, z as f32 + 1.0));
-            vertices.push(Vertex::new(x as f32 + 1.0, y as f32 + 1.0, z as f32 + 1.0));
-            vertices.push(Vertex::new(x as f32, y as f32 + 1.0, z as f32 + 1.0));
-
-            let voxel_indices = [
-                0, 1, 2, 2, 3, 0,
-                1, 5, 6, 6, 2, 1,
-                5, 4, 7, 7, 6, 5,
-                4, 0, 3, 3, 7, 4,
-                3, 2, 6, 6, 7, 3,
-                4, 5, 1, 1, 0, 4,
-            ];
-
-            for &index in &voxel_indices {
-                indices.push((index as u32) + index_offset);
+            for face in 0..FACES.len() {
+                self.push_face(current_voxel_pos, face, voxel.id, neighbours, mesh.part(voxel.id));
             }
-
-            index_offset += 8; // 8 vertices per voxel
         }
 
-        (vertices, indices)
+        mesh
     }
 
-    pub fn gen_mesh_data_culled(&self) -> (Vec<Vertex>, Vec<u32>) {
-        let mut vertices = Vec::new();
-        let mut indices = Vec::new();
+    pub fn gen_mesh_data_culled(&self, neighbours: &HashMap<Vector3<i32>, Chunk>) -> ChunkMesh {
+        let mut mesh = ChunkMesh::default();
 
         for current_voxel_pos in 0..CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE {
-            let x = current_voxel_pos / (CHUNK_SIZE * CHUNK_SIZE);
-            let y = (current_voxel_pos % (CHUNK_SIZE * CHUNK_SIZE)) / CHUNK_SIZE;
-            let z = current_voxel_pos % CHUNK_SIZE;
-
             let voxel = &self.voxels[current_voxel_pos];
-
             if voxel.id() == 0 {
                 continue;
             }
 
-            let mut visible_faces = [false; 6];
+            for (face, (normal, _)) in FACES.iter().enumerate() {
+                if self.is_visible(current_voxel_pos, *normal) {
+                    self.push_face(current_voxel_pos, face, voxel.id, neighbours, mesh.part(voxel.id));
+                }
+            }
+        }
 
-            if self.is_visible(current_voxel_pos, (-1, 0, 0)) { visible_faces[0] = true; }
-            if self.is_visible(current_voxel_pos, (1, 0, 0)) { visible_faces[1] = true; }
-            if self.is_visible(current_voxel_pos, (0, -1, 0)) { visible_faces[2] = true; }
-            if self.is_visible(current_voxel_pos, (0, 1, 0)) { visible_faces[3] = true; }
-            if self.is_visible(current_voxel_pos, (0, 0, -1)) { visible_faces[4] = true; }
-            if self.is_visible(current_voxel_pos, (0, 0, 1)) { visible_faces[5] = true; }
+        mesh
+    }
 
-            for (face_idx, &visible) in visible_faces.iter().enumerate() {
-                if !visible {
-                    continue;
-                }
+    // one quad of `FACES` with ambient occlusion from the blocks around its corners
+    fn push_face(&self, pos: usize, face: usize, block: u8, neighbours: &HashMap<Vector3<i32>, Chunk>, mesh: &mut MeshData) {
+        let x = (pos / (CHUNK_SIZE * CHUNK_SIZE)) as isize;
+        let y = ((pos % (CHUNK_SIZE * CHUNK_SIZE)) / CHUNK_SIZE) as isize;
+        let z = (pos % CHUNK_SIZE) as isize;
+        let ((nx, ny, nz), corners) = FACES[face];
+
+        let is_fluid = block::block(block).flow().is_some();
+        let biome = self.biomes[x as usize * CHUNK_SIZE + z as usize];
+
+        let start = mesh.vertices.len() as u32;
+        let mut ao = [0; 4];
+        for (i, (cx, cy, cz)) in corners.into_iter().enumerate() {
+            // the blocks touching this corner in the layer the face looks out into,
+            // stepping away from the voxel along both axes the face lies in
+            let step = |c: isize, n: isize| if n != 0 { 0 } else { c * 2 - 1 };
+            let (sx, sy, sz) = (step(cx, nx), step(cy, ny), step(cz, nz));
+            let (ox, oy, oz) = (x + nx, y + ny, z + nz);
+
+            let occluder = |x, y, z| self.is_occluder(x, y, z, neighbours);
+            let (side1, side2) = match (sx, sy, sz) {
+                (0, _, _) => (occluder(ox, oy + sy, oz), occluder(ox, oy, oz + sz)),
+                (_, 0, _) => (occluder(ox + sx, oy, oz), occluder(ox, oy, oz + sz)),
+                _ => (occluder(ox + sx, oy, oz), occluder(ox, oy + sy, oz)),
+            };
+            let corner = occluder(ox + sx, oy + sy, oz + sz);
+            ao[i] = if side1 && side2 { 0 } else { 3 - side1 as u32 - side2 as u32 - corner as u32 };
+
+            let drop = if cy == 1 && is_fluid { 16 - self.fluid_corner_height(x + cx, y, z + cz, block, neighbours) } else { 0 };
+            let corner = [(x + cx) as u32, (y + cy) as u32, (z + cz) as u32];
+            mesh.vertices.push(VoxelVertex::new(corner, face as u32, ao[i], MAX_LIGHT, block, face_uv(face, corner)).lowered(drop).in_biome(biome));
+        }
 
-                let start_vertex_idx = vertices.len() as u32;
-
-                match face_idx {
-                    0 => { // left
-                        vertices.push(Vertex::new(x as f32, y as f32, z as f32));
-                        vertices.push(Vertex::new(x as f32, y as f32, z as f32 + 1.0));
-                        vertices.push(Vertex::new(x as f32, y as f32 + 1.0, z as f32 + 1.0));
-                        vertices.push(Vertex::new(x as f32, y as f32 + 1.0, z as f32));
-                    },
-                    1 => { // right
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32, z as f32));
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32 + 1.0, z as f32));
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32 + 1.0, z as f32 + 1.0));
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32, z as f32 + 1.0));
-                    },
-                    2 => { // bottom
-                        vertices.push(Vertex::new(x as f32, y as f32, z as f32));
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32, z as f32));
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32, z as f32 + 1.0));
-                        vertices.push(Vertex::new(x as f32, y as f32, z as f32 + 1.0));
-                    },
-                    3 => { // top
-                        vertices.push(Vertex::new(x as f32, y as f32 + 1.0, z as f32));
-                        vertices.push(Vertex::new(x as f32, y as f32 + 1.0, z as f32 + 1.0));
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32 + 1.0, z as f32 + 1.0));
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32 + 1.0, z as f32));
-                    },
-                    4 => { // back
-                        vertices.push(Vertex::new(x as f32, y as f32, z as f32));
-                        vertices.push(Vertex::new(x as f32, y as f32 + 1.0, z as f32));
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32 + 1.0, z as f32));
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32, z as f32));
-                    },
-                    5 => { // front
-                        vertices.push(Vertex::new(x as f32, y as f32, z as f32 + 1.0));
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32, z as f32 + 1.0));
-                        vertices.push(Vertex::new(x as f32 + 1.0, y as f32 + 1.0, z as f32 + 1.0));
-                        vertices.push(Vertex::new(x as f32, y as f32 + 1.0, z as f32 + 1.0));
-                    },
-                    _ => {}
-                }
+        // split the quad along the diagonal with the brighter ends, otherwise the ao gradient
+        // comes out lopsided
+        let order = if ao[0] + ao[2] >= ao[1] + ao[3] { [0, 1, 2, 2, 3, 0] } else { [1, 2, 3, 3, 0, 1] };
+        mesh.indices.extend(order.map(|i| start + i));
+    }
 
-                indices.push(start_vertex_idx);
-                indices.push(start_vertex_idx + 1);
-                indices.push(start_vertex_idx + 2);
-                indices.push(start_vertex_idx + 2);
-                indices.push(start_vertex_idx + 3);
-                indices.push(start_vertex_idx);
+    // the surface height of fluid `id` at the top corner (x, y + 1, z), in 16ths of a block. averaged
+    // over the cells of that fluid sharing the corner so the surface slopes from one to the next,
+    // full height if any of them has more of it on top. cells past the border come from `neighbours`,
+    // the same corner then gets the same height from both chunks
+    fn fluid_corner_height(&self, x: isize, y: isize, z: isize, id: u8, neighbours: &HashMap<Vector3<i32>, Chunk>) -> u32 {
+        let (mut total, mut count) = (0, 0);
+        for (cx, cz) in [(x - 1, z - 1), (x - 1, z), (x, z - 1), (x, z)] {
+            match self.voxel_around(cx, y + 1, cz, neighbours) {
+                Some(above) if above.id() == id => return 16,
+                _ => {},
             }
+            if let Some(voxel) = self.voxel_around(cx, y, cz, neighbours).filter(|voxel| voxel.id() == id) {
+                total += fluid::surface_height(voxel);
+                count += 1;
+            }
+        }
+
+        total / count.max(1)
+    }
+
+    // a voxel by its position relative to this chunk, looked up in `neighbours` past the border
+    fn voxel_around<'a>(&'a self, x: isize, y: isize, z: isize, neighbours: &'a HashMap<Vector3<i32>, Chunk>) -> Option<&'a Voxel> {
+        let size = CHUNK_SIZE as isize;
+        if !(0..size).contains(&x) || !(0..size).contains(&y) || !(0..size).contains(&z) {
+            let origin = self.pos.cast::<i32>().unwrap() * CHUNK_SIZE as i32;
+            return voxel_in_chunks(neighbours, origin + Vector3::new(x as i32, y as i32, z as i32));
         }
 
-        (vertices, indices)
+        Some(&self.voxels[voxel_index(x as usize, y as usize, z as usize)])
+    }
+
+    // whether the voxel darkens the corners next to it. past the border it comes from `neighbours`, so
+    // faces along it shade the same as inside the chunk, unloaded counts as air
+    fn is_occluder(&self, x: isize, y: isize, z: isize, neighbours: &HashMap<Vector3<i32>, Chunk>) -> bool {
+        self.voxel_around(x, y, z, neighbours).is_some_and(|voxel| block::block(voxel.id()).is_opaque())
     }
 
     pub fn is_visible(&self, pos: usize, direction: (isize, isize, isize)) -> bool {
@@ -230,40 +331,77 @@ impl Chunk {
                         + ny as usize * CHUNK_SIZE
                         + nz as usize;
 
-        self.voxels[neighbor_pos].id() == 0
+        block::face_visible(self.voxels[pos].id(), self.voxels[neighbor_pos].id())
     }
 }
 
 use std::collections::{HashMap, VecDeque};
-use crate::mesh::Mesh;
+use crate::mesh_arena::MeshArena;
+use gl::{BlendFunc, DepthMask, Disable, Enable, BLEND, FALSE, ONE_MINUS_SRC_ALPHA, SRC_ALPHA, TRUE};
 use cgmath::Vector2;
 use crate::camera::Camera;
 use crate::shaders::*;
 use crate::shader::*;
 use std::ffi::CString;
 use cgmath::prelude::*;
-use crate::cstr;
-#[derive(Clone)]
+use crate::player::VoxelQuery;
+use std::sync::Arc;
+
+// what the last `World::draw` did, for the metrics overlay
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WorldStats {
+    pub meshing_time: f32, // seconds
+    pub chunks_meshed: usize,
+    pub draw_calls: usize,
+    pub chunks_drawn: usize,
+    pub translucent_chunks_drawn: usize,
+}
+
 pub struct World {
     pub chunks: HashMap<Vector3<i32>, Chunk>,
-    pub meshes: HashMap<Vector3<i32>, Mesh>,
+    pub meshes: MeshArena,
+    pub translucent_meshes: MeshArena,
+    visible: Vec<Vector3<i32>>, // chunks within view distance, from the last `draw`
     pub mesh_shader: Shader,
     pub camera_pos: Vector3<f32>,
+    pub render_distance: f32, // chunks closer than this get loaded
+    pub chunks_generated: usize,
+    pub stats: WorldStats,
+    pub generator: Arc<dyn TerrainGenerator>,
+    changes: Vec<(Vector3<i32>, Voxel)>, // voxels set since the last `take_changes`, with what they were before
 }
 
 impl World {
-    pub fn new() -> Self {
+    pub fn new() -> Result<Self, ShaderError> {
+        Self::with_generator(Arc::new(BiomeGenerator::new(0)))
+    }
+
+    pub fn with_generator(generator: Arc<dyn TerrainGenerator>) -> Result<Self, ShaderError> {
+        let mut world = Self::headless(generator);
+        world.mesh_shader = Shader::new_pipeline(MESH_SHADER_VS, MESH_SHADER_FS)?;
+
+        Ok(world)
+    }
+
+    // no gl calls, for tools that only generate and mesh. `draw` must not be called on it
+    pub fn headless(generator: Arc<dyn TerrainGenerator>) -> Self {
         let mut chunks = HashMap::new();
-        let chunk = Chunk::new(Vector3::new(0.0, 0.0, 0.0));
-        let mesh_shader_pipeline = Shader::new_pipeline(MESH_SHADER_VS, MESH_SHADER_FS);
+        let chunk = Chunk::with_generator(Vector3::new(0.0, 0.0, 0.0), generator.as_ref());
 
         chunks.insert(Vector3::new(0, 0, 0), chunk);
 
         Self {
-            meshes: HashMap::new(),
+            meshes: MeshArena::new(),
+            translucent_meshes: MeshArena::new(),
+            visible: Vec::new(),
             chunks,
             camera_pos: Vector3::zero(),
-            mesh_shader: mesh_shader_pipeline,
+            render_distance: 2.0,
+            chunks_generated: 0,
+            stats: WorldStats::default(),
+            mesh_shader: Shader::empty(),
+            generator,
+            changes: Vec::new(),
         }
     }
 
@@ -289,8 +427,23 @@ impl World {
         while let Some(current_pos) = queue.pop_front() {
             if !self.chunks.contains_key(&current_pos) {
                 let p1 = Vector3::new(current_pos.x as f32, current_pos.y as f32, current_pos.z as f32);
-                let chunk = Chunk::new(p1);
+                let chunk = Chunk::with_generator(p1, self.generator.as_ref());
                 self.chunks.insert(current_pos, chunk);
+                self.chunks_generated += 1;
+
+                // their border faces were shaded and sloped as if this one was air
+                for dx in -1..=1 {
+                    for dy in -1..=1 {
+                        for dz in -1..=1 {
+                            if (dx, dy, dz) == (0, 0, 0) {
+                                continue;
+                            }
+                            if let Some(neighbour) = self.chunks.get_mut(&(current_pos + Vector3::new(dx, dy, dz))) {
+                                neighbour.is_mesh = false;
+                            }
+                        }
+                    }
+                }
             }
 
             for direction in &directions {
@@ -298,7 +451,7 @@ impl World {
                 let p1 = Vector3::new(new_pos.x as f32, new_pos.y as f32, new_pos.z as f32);
                 let p2 = Vector3::new(chunk_pos.x as f32, chunk_pos.y as f32, chunk_pos.z as f32);
 
-                if !self.chunks.contains_key(&new_pos) && p1.distance(p2) <= 2.0 {
+                if !self.chunks.contains_key(&new_pos) && p1.distance(p2) <= self.render_distance {
                     queue.push_back(new_pos);
                 }
             }
@@ -313,60 +466,109 @@ impl World {
             let chunk_data = self.chunks.get(chunk).unwrap();
             let time = (std::time::Instant::now() - chunk_data.creation_instant)
                 .as_secs_f32();
-            if p1.distance(p2) > 4.0 {
+            if p1.distance(p2) > self.render_distance * 2.0 {
                 chunks_to_remove.push(pos);
             }
         }
         for pos in chunks_to_remove {
             self.meshes.remove(&pos);
+            self.translucent_meshes.remove(&pos);
             self.chunks.remove(&pos);
         }
     }
 
+    // view/proj come from the camera uniform block, written once per frame by the caller
     pub fn draw(&mut self, camera: &Camera) {
         let chunk_pos = Vector3::new(
             (camera.pos_x.x / CHUNK_SIZE as f32).floor() as i32, 
             (camera.pos_x.y / CHUNK_SIZE as f32).floor() as i32, 
             (camera.pos_x.z / CHUNK_SIZE as f32).floor() as i32
         );
+        self.stats = WorldStats::default();
+        self.visible.clear();
+
+        // meshing reads the chunks around too, so they're all meshed before any is marked done
+        let stale: Vec<_> = self.chunks.iter().filter(|(_, chunk)| !chunk.is_mesh).map(|(pos, _)| *pos).collect();
+        for pos in stale {
+            let start = std::time::Instant::now();
+            let mesh = self.chunks[&pos].gen_mesh_data_culled(&self.chunks);
+            self.stats.meshing_time += start.elapsed().as_secs_f32();
+            self.stats.chunks_meshed += 1;
+
+            unsafe {
+                self.meshes.insert(pos, &mesh.solid.vertices, &mesh.solid.indices);
+                self.translucent_meshes.insert(pos, &mesh.translucent.vertices, &mesh.translucent.indices);
+            }
+            if let Some(chunk) = self.chunks.get_mut(&pos) {
+                chunk.is_mesh = true;
+            }
+        }
 
-        for chunk in &mut self.chunks {
-            let pos = chunk.0;
-
+        for pos in self.chunks.keys() {
             let p1 = Vector3::new(pos.x as f32, pos.y as f32, pos.z as f32);
             let p2 = Vector3::new(chunk_pos.x as f32, chunk_pos.y as f32, chunk_pos.z as f32);
 
-            if !chunk.1.is_mesh {
-                let mesh_data = chunk.1.gen_mesh_data_culled();
-                let mesh = Mesh::new(mesh_data.0, mesh_data.1);
-                chunk.1.is_mesh = true;
-                
-                if let Some(mesh) = self.meshes.get_mut(pos) {
-                    unsafe { mesh.destroy(); }
-                }
-                self.meshes.remove(pos);
-                self.meshes.insert(*pos, mesh);
+            if p2.distance(p1) <= self.render_distance * 4.0 {
+                self.visible.push(*pos);
             }
+        }
 
-            if let Some(mesh) = self.meshes.get(pos) {
-                unsafe {
-                    camera.send_uniforms(&self.mesh_shader);
-                    self.mesh_shader.uniform_vec3f(cstr!("chunkPos"), 
-                        &Vector3::new(
-                            (pos.x * CHUNK_SIZE as i32) as f32, 
-                            (pos.y * CHUNK_SIZE as i32) as f32, 
-                            (pos.z * CHUNK_SIZE as i32) as f32
-                        )
-                    );
-                    if p2.distance(p1) > 8.0 { 
-                    } else {
-                        mesh.draw(&self.mesh_shader);
-                    }
-                }
-            }
+        unsafe {
+            self.mesh_shader.use_shader();
+            self.stats.chunks_drawn = self.meshes.draw(&self.mesh_shader, self.visible.iter().copied());
+        }
+        self.stats.draw_calls = (self.stats.chunks_drawn > 0) as usize;
+    }
+
+    // the blended blocks of the chunks `draw` drew, after everything solid. chunks go back to front
+    // so water blends over what's behind it. they test depth but don't write it, so a blended face
+    // never hides another one drawn after it
+    pub fn draw_translucent(&mut self, camera: &Camera) {
+        let half = CHUNK_SIZE as f32 * 0.5;
+        let distance = |pos: &Vector3<i32>| {
+            let center = (pos * CHUNK_SIZE as i32).cast::<f32>().unwrap() + Vector3::new(half, half, half);
+            center.distance2(camera.pos_x)
+        };
+
+        let mut chunks: Vec<_> = self.visible.iter().copied().filter(|pos| self.translucent_meshes.contains(pos)).collect();
+        chunks.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+
+        unsafe {
+            Enable(BLEND);
+            BlendFunc(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+            DepthMask(FALSE);
+            self.mesh_shader.use_shader();
+            let drawn = self.translucent_meshes.draw(&self.mesh_shader, chunks);
+            DepthMask(TRUE);
+            Disable(BLEND);
+
+            self.stats.translucent_chunks_drawn = drawn;
+            self.stats.draw_calls += (drawn > 0) as usize;
         }
     }
 
+    // the solid meshes from the last `draw` into a shadow cascade, with `shader` already in use.
+    // glass and leaves are in there too, the shadow shader cuts their see-through texels out.
+    // skips chunks whose shadow can't land within `radius` of `center` looking along
+    // `light_direction`. returns the number of chunks drawn
+    pub unsafe fn draw_depth(&self, shader: &Shader, center: Vector3<f32>, radius: f32, light_direction: Vector3<f32>) -> usize {
+        let half = CHUNK_SIZE as f32 * 0.5;
+        let reach = radius + half * 3f32.sqrt(); // plus the chunk's own bounding sphere
+
+        let casters = self.meshes.positions().copied().filter(|pos| {
+            let origin = (pos * CHUNK_SIZE as i32).cast::<f32>().unwrap();
+            let offset = origin + Vector3::new(half, half, half) - center;
+            let across = offset - light_direction * offset.dot(light_direction);
+            across.magnitude() <= reach
+        });
+
+        self.meshes.draw(shader, casters)
+    }
+
+    pub fn voxel_at(&self, pos: Vector3<i32>) -> Option<&Voxel> {
+        voxel_in_chunks(&self.chunks, pos)
+    }
+
     pub fn chunk_in_camera(&mut self, pos: Vector3<f32>) -> Option<&mut Chunk> {
         let chunk_pos = Vector3::new(
             (pos.x / CHUNK_SIZE as f32).floor() as i32, 
@@ -377,29 +579,228 @@ impl World {
         self.chunks.get_mut(&chunk_pos)
     }
 
-    pub fn remove_voxel_raycasting(&mut self, cam_pos: Vector3<f32>, dir: Vector3<f32>) {
-        let mut curr_pos = cam_pos;
-        let step_size = 0.1;
-        let max_steps = (5.0 / step_size) as usize; // maximum distance is 5
-
-        for _ in 0..max_steps {
-            let chunk_pos = Vector3::new(
-                (curr_pos.x / CHUNK_SIZE as f32).floor() as i32,
-                (curr_pos.y / CHUNK_SIZE as f32).floor() as i32,
-                (curr_pos.z / CHUNK_SIZE as f32).floor() as i32,
-            );
-
-            if let Some(chunk) = self.chunks.get_mut(&chunk_pos) {
-                let local_pos = curr_pos - chunk_pos.cast::<f32>().unwrap() * CHUNK_SIZE as f32;
-                let voxel_idx = Chunk::get_voxel(local_pos);
-
-                if chunk.voxels[voxel_idx].id != 0 {
-                    chunk.destroy_voxel(local_pos);
-                    return;
+    // writes a voxel at a world voxel coordinate, returns false if its chunk isn't loaded
+    pub fn set_voxel(&mut self, pos: Vector3<i32>, voxel: Voxel) -> bool {
+        let (chunk_pos, (x, y, z)) = world_to_chunk(pos);
+        let Some(chunk) = self.chunks.get_mut(&chunk_pos) else {
+            return false;
+        };
+
+        let previous = *chunk.voxel(x, y, z);
+        chunk.set_voxel(x, y, z, voxel);
+        self.changes.push((pos, previous));
+
+        // the chunks across a border mesh their fluid surfaces and ambient occlusion from this voxel too
+        let border = |c: usize| if c == 0 { -1 } else if c == CHUNK_SIZE - 1 { 1 } else { 0 };
+        let (bx, by, bz) = (border(x), border(y), border(z));
+        for dx in [0, bx] {
+            for dy in [0, by] {
+                for dz in [0, bz] {
+                    if (dx, dy, dz) == (0, 0, 0) {
+                        continue;
+                    }
+                    if let Some(neighbour) = self.chunks.get_mut(&(chunk_pos + Vector3::new(dx, dy, dz))) {
+                        neighbour.is_mesh = false;
+                    }
                 }
             }
+        }
+
+        true
+    }
+
+    // every voxel position `set_voxel` changed since the last call, for whatever reacts to edits
+    pub fn take_changes(&mut self) -> Vec<(Vector3<i32>, Voxel)> {
+        std::mem::take(&mut self.changes)
+    }
+
+    // first solid voxel along the ray, stepping voxel boundary to voxel boundary (amanatides & woo)
+    pub fn raycast(&self, origin: Vector3<f32>, dir: Vector3<f32>, max_distance: f32) -> Option<RaycastHit> {
+        if dir.magnitude2() == 0.0 {
+            return None;
+        }
+        let dir = dir.normalize();
+
+        let mut voxel = Vector3::new(origin.x.floor() as i32, origin.y.floor() as i32, origin.z.floor() as i32);
+        let mut step = Vector3::new(0, 0, 0);
+        let mut next_boundary = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY); // ray distance to the next boundary per axis
+        let mut delta = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY); // ray distance between boundaries per axis
+
+        for axis in 0..3 {
+            if dir[axis] > 0.0 {
+                step[axis] = 1;
+                delta[axis] = 1.0 / dir[axis];
+                next_boundary[axis] = (voxel[axis] as f32 + 1.0 - origin[axis]) * delta[axis];
+            } else if dir[axis] < 0.0 {
+                step[axis] = -1;
+                delta[axis] = -1.0 / dir[axis];
+                next_boundary[axis] = (origin[axis] - voxel[axis] as f32) * delta[axis];
+            }
+        }
+
+        let mut normal = Vector3::new(0, 0, 0);
+        let mut distance = 0.0;
+        while distance <= max_distance {
+            if let Some(id) = self.voxel_at(voxel).map(Voxel::id).filter(|id| block::block(*id).solid) {
+                return Some(RaycastHit { voxel, normal, id, distance });
+            }
+
+            let axis = if next_boundary.x < next_boundary.y {
+                if next_boundary.x < next_boundary.z { 0 } else { 2 }
+            } else if next_boundary.y < next_boundary.z { 1 } else { 2 };
+
+            distance = next_boundary[axis];
+            next_boundary[axis] += delta[axis];
+            voxel[axis] += step[axis];
+            normal = Vector3::new(0, 0, 0);
+            normal[axis] = -step[axis];
+        }
+
+        None
+    }
 
-            curr_pos += dir * step_size;
+    pub fn remove_voxel_raycasting(&mut self, cam_pos: Vector3<f32>, dir: Vector3<f32>) {
+        if let Some(hit) = self.raycast(cam_pos, dir, REACH) {
+            self.destroy_voxel(hit.voxel);
         }
     }
-}
\ No newline at end of file
+
+    // leaves air behind. like every `set_voxel` it's logged, so `BlockUpdates` wakes up whatever
+    // was resting on or flowing against it
+    pub fn destroy_voxel(&mut self, pos: Vector3<i32>) -> bool {
+        self.set_voxel(pos, Voxel::air())
+    }
+
+    // against the targeted face, only into air. returns whether anything was placed
+    pub fn place_voxel_raycasting(&mut self, cam_pos: Vector3<f32>, dir: Vector3<f32>, voxel: Voxel) -> bool {
+        let Some(hit) = self.raycast(cam_pos, dir, REACH) else {
+            return false;
+        };
+        let pos = hit.voxel + hit.normal;
+        if hit.normal == Vector3::zero() || self.voxel_at(pos).is_none_or(|v| v.id() != block::AIR) {
+            return false;
+        }
+
+        self.set_voxel(pos, voxel)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RaycastHit {
+    pub voxel: Vector3<i32>,
+    pub normal: Vector3<i32>, // face of `voxel` the ray entered through, zero if it started inside
+    pub id: u8,
+    pub distance: f32,
+}
+
+// splits a world voxel coordinate into the chunk that owns it and the voxel inside that chunk
+pub fn world_to_chunk(pos: Vector3<i32>) -> (Vector3<i32>, (usize, usize, usize)) {
+    let size = CHUNK_SIZE as i32;
+    let chunk = Vector3::new(pos.x.div_euclid(size), pos.y.div_euclid(size), pos.z.div_euclid(size));
+    let local = (
+        pos.x.rem_euclid(size) as usize,
+        pos.y.rem_euclid(size) as usize,
+        pos.z.rem_euclid(size) as usize,
+    );
+
+    (chunk, local)
+}
+
+pub fn voxel_in_chunks(chunks: &HashMap<Vector3<i32>, Chunk>, pos: Vector3<i32>) -> Option<&Voxel> {
+    let (chunk_pos, (x, y, z)) = world_to_chunk(pos);
+    chunks.get(&chunk_pos).map(|chunk| chunk.voxel(x, y, z))
+}
+
+impl VoxelQuery for World {
+    fn is_solid(&self, pos: Vector3<i32>) -> bool {
+        self.chunks.is_solid(pos)
+    }
+}
+
+// lets physics run against a bare set of chunks, without a gl context
+impl VoxelQuery for HashMap<Vector3<i32>, Chunk> {
+    fn is_solid(&self, pos: Vector3<i32>) -> bool {
+        voxel_in_chunks(self, pos).is_some_and(|voxel| block::block(voxel.id()).solid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use cgmath::{vec3, InnerSpace};
+
+    // all air, the tests place what they need
+    struct Empty;
+
+    impl TerrainGenerator for Empty {
+        fn generate(&self, _chunk_pos: Vector3<f32>, _voxels: &mut [Voxel], _biomes: &mut [u8]) {}
+    }
+
+    // the chunk at the origin and the one to its -x, with ground at `blocks`
+    fn world_with(blocks: &[Vector3<i32>]) -> World {
+        let mut world = World::headless(Arc::new(Empty));
+        world.chunks.insert(Vector3::new(-1, 0, 0), Chunk::empty(Vector3::new(-1.0, 0.0, 0.0)));
+        for &pos in blocks {
+            assert!(world.set_voxel(pos, Voxel::ground()));
+        }
+        world
+    }
+
+    fn assert_hit(hit: Option<RaycastHit>, voxel: Vector3<i32>, normal: Vector3<i32>, distance: f32) {
+        let hit = hit.expect("missed");
+        assert_eq!((hit.voxel, hit.normal, hit.id), (voxel, normal, block::GROUND));
+        assert!((hit.distance - distance).abs() < 1e-4, "{} != {distance}", hit.distance);
+    }
+
+    #[test]
+    fn raycast_along_an_axis() {
+        let world = world_with(&[Vector3::new(5, 5, 10), Vector3::new(5, 5, 12)]);
+        // the nearer one, through the face towards the origin
+        assert_hit(world.raycast(vec3(5.5, 5.5, 2.5), vec3(0.0, 0.0, 1.0), 20.0), Vector3::new(5, 5, 10), Vector3::new(0, 0, -1), 7.5);
+        assert_hit(world.raycast(vec3(5.5, 20.0, 10.5), vec3(0.0, -3.0, 0.0), 20.0), Vector3::new(5, 5, 10), Vector3::new(0, 1, 0), 14.0);
+        assert!(world.raycast(vec3(5.5, 5.5, 2.5), vec3(0.0, 0.0, -1.0), 20.0).is_none());
+        assert!(world.raycast(vec3(5.5, 5.5, 2.5), Vector3::zero(), 20.0).is_none());
+    }
+
+    #[test]
+    fn raycast_diagonally() {
+        let world = world_with(&[Vector3::new(8, 8, 8)]);
+        let origin = vec3(2.2, 2.5, 2.7);
+        // enters through the face whose plane it reaches last, x here
+        let dir = vec3(1.0, 1.0, 1.0);
+        assert_hit(world.raycast(origin, dir, 20.0), Vector3::new(8, 8, 8), Vector3::new(-1, 0, 0), 5.8 * 3f32.sqrt());
+
+        // the direction doesn't need to be normalized, distances are in blocks either way
+        let target = vec3(8.5, 8.5, 8.5);
+        let hit = world.raycast(origin, (target - origin) * 3.0, 20.0).unwrap();
+        assert_eq!(hit.voxel, Vector3::new(8, 8, 8));
+        assert!(hit.distance > 0.0 && hit.distance < (target - origin).magnitude());
+    }
+
+    #[test]
+    fn raycast_across_a_negative_border() {
+        let world = world_with(&[Vector3::new(-3, 4, 6)]);
+        assert_hit(world.raycast(vec3(1.5, 4.5, 6.5), vec3(-1.0, 0.0, 0.0), 20.0), Vector3::new(-3, 4, 6), Vector3::new(1, 0, 0), 3.5);
+        // and back out of it
+        let world = world_with(&[Vector3::new(0, 4, 6)]);
+        assert_hit(world.raycast(vec3(-2.5, 4.5, 6.5), vec3(1.0, 0.0, 0.0), 20.0), Vector3::new(0, 4, 6), Vector3::new(-1, 0, 0), 2.5);
+    }
+
+    #[test]
+    fn raycast_from_inside_a_block() {
+        let world = world_with(&[Vector3::new(5, 5, 10)]);
+        assert_hit(world.raycast(vec3(5.5, 5.2, 10.9), vec3(0.0, 1.0, 0.0), 20.0), Vector3::new(5, 5, 10), Vector3::zero(), 0.0);
+    }
+
+    #[test]
+    fn raycast_stops_at_max_distance() {
+        let world = world_with(&[Vector3::new(5, 5, 10)]);
+        let (origin, dir) = (vec3(5.5, 5.5, 2.5), vec3(0.0, 0.0, 1.0));
+        assert!(world.raycast(origin, dir, 7.4).is_none());
+        assert_hit(world.raycast(origin, dir, 7.5), Vector3::new(5, 5, 10), Vector3::new(0, 0, -1), 7.5);
+        // unloaded chunks are air, the ray keeps going through them
+        assert!(world.raycast(vec3(5.5, 5.5, 9.5), vec3(0.0, 0.0, -1.0), 100.0).is_none());
+    }
+}